@@ -1,17 +1,26 @@
 extern crate ncurses;
 
 use ncurses::*;
+use crate::colour::ColourManager;
 use crate::visuals::VisualsRegistry;
-use std::sync::atomic::{AtomicI32, Ordering};
+use std::cell::OnceCell;
 use std::ops::Drop;
+use std::rc::Rc;
 
 pub struct NcursesContext {
-    visuals: VisualsRegistry,
+    colours: Rc<ColourManager>,
+    visuals: OnceCell<VisualsRegistry>,
 }
 
 impl NcursesContext {
+    /// Built on first use from the context's colour manager.
     pub fn get_visuals(&self) -> &VisualsRegistry {
-        &self.visuals
+        self.visuals.get_or_init(|| VisualsRegistry::build(self.get_colours()))
+    }
+
+    /// The colour manager every colour pair of the game comes from.
+    pub fn get_colours(&self) -> Rc<ColourManager> {
+        Rc::clone(&self.colours)
     }
 
     pub fn get_last_pressed_key(&self) -> Option<i32> {
@@ -42,18 +51,67 @@ impl NcursesContext {
     }
 }
 
+// Keys getch gives as plain characters, which ncurses has no names for.
+pub const KEY_TAB: i32 = 9;
+pub const KEY_NEWLINE: i32 = 10;
+pub const KEY_ESCAPE: i32 = 27;
+pub const KEY_DELETE: i32 = 127;
+
+/// Milliseconds to wait for the rest of a key sequence after escape.
+const ESCAPE_DELAY: i32 = 25;
+
+/// Leaves curses before a panic is reported, so the message and the
+/// backtrace end up on a usable terminal.
+fn restore_terminal_on_panic() {
+    let report = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        cleanup();
+        report(info);
+    }));
+}
+
 pub fn initialize() -> Option<NcursesContext> {
-    initscr();
-    start_color();
+    setlocale(LcCategory::all, "");
+    if initscr().is_null() {
+        return None;
+    }
+    restore_terminal_on_panic();
+    noecho();
+    // Escape cancels menus, so it should not wait long for a sequence.
+    set_escdelay(ESCAPE_DELAY);
+    if has_colors() {
+        start_color();
+    }
     curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
     refresh();
     Some(NcursesContext{
-        visuals: VisualsRegistry::build()?
+        colours: Rc::new(ColourManager::new()),
+        visuals: OnceCell::new(),
     })
 }
 
+/// The screen's height and width.
+pub fn screen_size() -> (i32, i32) {
+    (LINES(), COLS())
+}
+
+pub fn clear_screen() {
+    erase();
+    refresh();
+}
+
+/// Clears the screen and shows `text` in its middle.
+pub fn show_message(text: &str) {
+    erase();
+    mvaddstr(LINES() / 2, ((COLS() - text.len() as i32) / 2).max(0), text);
+    refresh();
+}
+
+/// Leaves curses mode, unless a panic already did.
 pub fn cleanup() {
-    endwin();
+    if !isendwin() {
+        endwin();
+    }
 }
 
 impl Drop for NcursesContext {
@@ -63,13 +121,12 @@ impl Drop for NcursesContext {
 }
 
 
-static _NEXT_FREE_COLOR_ID: AtomicI32 = AtomicI32::new(1);
-
 #[derive(Copy, Clone)]
 pub struct ColorPair {
     id: i16
 }
 
+#[derive(Copy, Clone)]
 pub struct Dimensions {
     x: i32,
     y: i32,
@@ -81,17 +138,58 @@ impl Dimensions {
     pub fn new(x: i32, y: i32, x_size: i32, y_size: i32) -> Dimensions {
         Dimensions{x, y, x_size, y_size}
     }
+
+    /// The whole screen.
+    pub fn screen() -> Dimensions {
+        let (lines, cols) = screen_size();
+        Dimensions::new(0, 0, lines, cols)
+    }
+
+    /// A window of the given size in the middle of the screen, cut down
+    /// to the screen when it is larger.
+    pub fn centred(x_size: i32, y_size: i32) -> Dimensions {
+        Dimensions::screen().centred_in(x_size, y_size)
+    }
+
+    /// An area of the given size in the middle of this one, cut down to
+    /// this one when it is larger.
+    pub fn centred_in(&self, x_size: i32, y_size: i32) -> Dimensions {
+        let (x_size, y_size) = (x_size.min(self.x_size), y_size.min(self.y_size));
+        Dimensions::new(self.x + (self.x_size - x_size) / 2, self.y + (self.y_size - y_size) / 2, x_size, y_size)
+    }
+
+    /// The area left inside a border of `margin` cells.
+    pub fn inset(&self, margin: i32) -> Dimensions {
+        Dimensions::new(
+            self.x + margin,
+            self.y + margin,
+            (self.x_size - 2 * margin).max(0),
+            (self.y_size - 2 * margin).max(0),
+        )
+    }
+
+    pub fn line(&self) -> i32 {
+        self.x
+    }
+
+    pub fn column(&self) -> i32 {
+        self.y
+    }
+
+    pub fn height(&self) -> i32 {
+        self.x_size
+    }
+
+    pub fn width(&self) -> i32 {
+        self.y_size
+    }
 }
 
 impl ColorPair {
-    pub fn new(fg: i16, bg: i16) -> Option<ColorPair> {
-        let assigned_id = _NEXT_FREE_COLOR_ID.fetch_add(1, Ordering::Relaxed);
-        if COLOR_PAIRS() <= assigned_id {
-            None
-        } else {
-            init_pair(assigned_id as i16, fg, bg);
-            Some(ColorPair{id: assigned_id as i16})
-        }
+    /// Defines pair `id`. Pairs are handed out by the `ColourManager`.
+    pub fn init(id: i16, fg: i16, bg: i16) -> ColorPair {
+        init_pair(id, fg, bg);
+        ColorPair { id }
     }
 }
 
@@ -103,11 +201,11 @@ impl Into<Attributes> for ColorPair {
 
 #[derive(Copy, Clone)]
 pub struct Attributes {
-    value: u64
+    value: chtype
 }
 
 impl Attributes {
-    pub fn new(attr: u64) -> Attributes {
+    pub fn new(attr: attr_t) -> Attributes {
         Attributes { value: attr }
     }
 
@@ -125,25 +223,44 @@ impl std::ops::BitOr<Attributes> for Attributes {
     }
 }
 
-impl std::ops::BitOr<u64> for Attributes {
+impl std::ops::BitOr<attr_t> for Attributes {
 
     type Output = Attributes;
 
-    fn bitor(self, rhs: u64) -> Self::Output {
+    fn bitor(self, rhs: attr_t) -> Self::Output {
         Attributes{value: self.value | rhs}
     }
 }
 
 #[derive(Copy, Clone)]
 pub struct PrintableCharacter {
-    value: u64
+    value: chtype
 }
 
 impl PrintableCharacter {
     pub fn new(c: char, attr: Attributes) -> PrintableCharacter {
-        PrintableCharacter { value: c as u64 | attr.value}
+        PrintableCharacter { value: c as chtype | attr.value}
     }
 
+    /// The same attributes with another character.
+    pub fn with_char(&self, c: char) -> PrintableCharacter {
+        PrintableCharacter { value: (self.value & !A_CHARTEXT()) | c as chtype }
+    }
+
+}
+
+/// A character outside the range of chtype, printed as a string through
+/// the wide character version of curses.
+#[derive(Copy, Clone)]
+pub struct WideCharacter {
+    value: char,
+    attr: Attributes,
+}
+
+impl WideCharacter {
+    pub fn new(c: char, attr: Attributes) -> WideCharacter {
+        WideCharacter { value: c, attr }
+    }
 }
 
 pub struct BasicWindow {
@@ -161,6 +278,18 @@ impl BasicWindow {
         wrefresh(self.win);
     }
 
+    pub fn erase(&self) {
+        werase(self.win);
+    }
+
+    pub fn height(&self) -> i32 {
+        getmaxy(self.win)
+    }
+
+    pub fn width(&self) -> i32 {
+        getmaxx(self.win)
+    }
+
     pub fn move_cur(&self, x: i32, y: i32) {
         wmove(self.win, x, y);
     }
@@ -173,6 +302,15 @@ impl BasicWindow {
         mvwaddstr(self.win, x, y, s);
     }
 
+    pub fn clear_to_end_of_line(&self) {
+        wclrtoeol(self.win);
+    }
+
+    /// Draws a line along the edges of the window.
+    pub fn draw_border(&self) {
+        box_(self.win, 0, 0);
+    }
+
     pub fn put_character(&self, ch: PrintableCharacter) {
         waddch(self.win, ch.value);
     }
@@ -182,6 +320,12 @@ impl BasicWindow {
         self.put_character(ch);
     }
 
+    pub fn put_wide(&self, ch: WideCharacter) {
+        self.set_attr(ch.attr);
+        waddstr(self.win, ch.value.encode_utf8(&mut [0; 4]));
+        self.clear_attr();
+    }
+
     pub fn apply_attr(&self, attr: Attributes) {
         wattr_on(self.win, attr.value);
     }
@@ -210,4 +354,12 @@ pub trait Window : {
     fn refresh(&self);
     fn draw(&self);
     fn handle_keypress(&mut self, key: i32);
+
+    /// Whether the window takes keypresses when it is given the focus.
+    fn focusable(&self) -> bool {
+        false
+    }
+
+    /// Shows or hides that the window has the focus.
+    fn set_focus(&mut self, _focused: bool) {}
 }
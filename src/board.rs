@@ -1,8 +1,13 @@
 use std::vec;
 use std::ops::{Index, IndexMut};
-use std::collections::VecDeque;
+use std::collections::{VecDeque, HashSet};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 
+use crate::board_metadata::BoardMetadata;
+use crate::rules::GameRules;
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum Direction {
     Up = 0,
@@ -13,6 +18,19 @@ pub enum Direction {
 
 impl Direction {
 
+    pub const ALL: [Direction; 4] = [Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+
+    /// The arrows snake segments are written with, in the order of `ALL`.
+    const ARROWS: [char; 4] = ['^', '>', 'v', '<'];
+
+    pub fn arrow(&self) -> char {
+        Direction::ARROWS[*self as usize]
+    }
+
+    pub fn from_arrow(c: char) -> Option<Direction> {
+        Direction::ARROWS.iter().position(|arrow| *arrow == c).map(|i| Direction::ALL[i])
+    }
+
     pub fn from_primitive(x: u8) -> Direction {
         let x = x % 4;
         match x {
@@ -34,7 +52,7 @@ impl Direction {
 
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Position(pub usize, pub usize);
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -44,14 +62,112 @@ pub struct PortalData {
     pub rotation: u8,
 }
 
+/// A single cell of either board layer. Walls, portals, gates, doors, ice
+/// and conveyors live in the terrain layer; the snake, food and keys are
+/// occupants that can sit on top of walkable terrain.
 #[derive(Clone, PartialEq, Eq)]
 pub enum BrickType {
     None,
     Wall,
-    Snake(Direction),
+    /// A body segment with the direction the snake entered it in and the
+    /// one it left it in. They differ on corners and next to portals.
+    Snake(Direction, Direction),
     SnakeHead(Direction),
     Food,
     Portal(Box<PortalData>),
+    Gate(Direction),
+    Key(i16),
+    Door(i16),
+    Ice,
+    Conveyor(Direction),
+    /// A wall belonging to a switchable group, with its current solidity.
+    GroupWall(usize, bool),
+    Switch(usize),
+    DynamicWall,
+}
+
+pub struct WallGroup {
+    pub name: String,
+    pub cells: Vec<Position>,
+    pub solid: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum WallMotion {
+    /// Solid for `on` ticks out of every period.
+    Timed { on: u64 },
+    /// Shifts one cell every period, back and forth over `distance` cells.
+    Moving { dir: Direction, distance: usize },
+}
+
+pub struct DynamicWall {
+    pub pos: Position,
+    pub len: usize,
+    pub dir: Direction,
+    pub period: u64,
+    pub phase: u64,
+    pub motion: WallMotion,
+    pub cells: Vec<Position>,
+}
+
+impl DynamicWall {
+    /// The cells the wall covers when shifted `offset` cells along its
+    /// motion, or None when part of it would be off the board.
+    fn cells_shifted(&self, board: &Board, offset: usize) -> Option<Vec<Position>> {
+        let Position(x, y) = self.pos;
+        if x >= board.x_size() || y >= board.y_size() {
+            return None;
+        }
+        let mut pos = self.pos;
+        if let WallMotion::Moving { dir, .. } = self.motion {
+            for _ in 0..offset {
+                pos = board.neighbour(pos, dir)?;
+            }
+        }
+        let mut cells = Vec::with_capacity(self.len);
+        for i in 0..self.len {
+            if i > 0 {
+                pos = board.neighbour(pos, self.dir)?;
+            }
+            cells.push(pos);
+        }
+        Some(cells)
+    }
+
+    /// Whether the wall stays on the board all along its motion.
+    pub fn fits(&self, board: &Board) -> bool {
+        let distance = match self.motion {
+            WallMotion::Timed { .. } => 0,
+            WallMotion::Moving { distance, .. } => distance,
+        };
+        (0..=distance).all(|offset| self.cells_shifted(board, offset).is_some())
+    }
+
+    pub fn cells_at(&self, board: &Board, tick: u64) -> Vec<Position> {
+        let time = tick + self.phase;
+        let (offset, shown) = match self.motion {
+            WallMotion::Timed { on } => (0, time % self.period < on),
+            WallMotion::Moving { distance, .. } => {
+                let cycle = 2 * distance as u64;
+                let step = if cycle == 0 { 0 } else { (time / self.period) % cycle };
+                let offset = if step <= distance as u64 { step } else { cycle - step };
+                (offset as usize, true)
+            }
+        };
+        if !shown {
+            return Vec::new();
+        }
+        self.cells_shifted(board, offset).unwrap_or_default()
+    }
+}
+
+impl BrickType {
+    pub fn is_floor(&self) -> bool {
+        matches!(self,
+            BrickType::None | BrickType::Ice | BrickType::Conveyor(_) |
+            BrickType::GroupWall(_, false) | BrickType::Switch(_)
+        )
+    }
 }
 
 impl Position {
@@ -71,17 +187,37 @@ pub struct Board {
     y_size: usize,
     pub facing: Direction,
     pub last_step: Direction,
-    pub board: Vec::<Vec::<BrickType>>,
+    pub occupants: Vec::<Vec::<BrickType>>,
+    pub terrain: Vec::<Vec::<BrickType>>,
     pub snake_pos: Position,
     pub snake: VecDeque<Position>,
+    /// The direction the head entered its cell in, kept apart from the
+    /// facing the player can still change.
+    pub head_entered: Direction,
     pub initial_size: usize,
+    /// Colours of the keys collected. Their doors are opened on pickup.
+    pub keys: HashSet<i16>,
+    pub wall_groups: Vec<WallGroup>,
+    pub dynamic_walls: Vec<DynamicWall>,
+    pub tick: u64,
+    pub food_eaten: usize,
+    pub metadata: BoardMetadata,
+    pub rules: GameRules,
+    /// The seed a generated board was made from.
+    pub seed: Option<u64>,
+    /// The seed food spawns are drawn from: random, unless the board has
+    /// to play out the same.
+    pub food_seed: u64,
+    pub food_rng: StdRng,
+    /// Segments the snake still has to grow by from food already eaten.
+    pub pending_growth: usize,
 }
 
 impl Index<usize> for Board {
     type Output = Vec::<BrickType>;
 
     fn index(&self, index: usize) -> &Self::Output {
-        &self.board[index]
+        &self.occupants[index]
     }
 }
 
@@ -95,7 +231,7 @@ impl Index<Position> for Board {
 
 impl IndexMut<usize> for Board {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.board[index]
+        &mut self.occupants[index]
     }
 }
 
@@ -107,15 +243,29 @@ impl IndexMut<Position> for Board {
 
 impl Board {
     pub fn new_empty(x_size: usize, y_size: usize) -> Board {
+        let food_seed = rand::random();
         Board {
             x_size,
             y_size,
             facing: Direction::Up,
             last_step: Direction::Up,
-            board: vec![vec![BrickType::None; y_size]; x_size],
+            occupants: vec![vec![BrickType::None; y_size]; x_size],
+            terrain: vec![vec![BrickType::None; y_size]; x_size],
             snake_pos: Position(0, 0),
             snake: VecDeque::new(),
-            initial_size: 0
+            head_entered: Direction::Up,
+            initial_size: 0,
+            keys: HashSet::new(),
+            wall_groups: Vec::new(),
+            dynamic_walls: Vec::new(),
+            tick: 0,
+            food_eaten: 0,
+            metadata: BoardMetadata::default(),
+            rules: GameRules::default(),
+            seed: None,
+            food_seed,
+            food_rng: StdRng::seed_from_u64(food_seed),
+            pending_growth: 0,
         }
     }
 
@@ -131,32 +281,136 @@ impl Board {
 
     fn place_snake(&mut self, x: usize, y: usize) {
         for i in (x..x + self.initial_size).rev() {
-            self[i][y] = BrickType::Snake(Direction::Up);
+            self[i][y] = BrickType::Snake(Direction::Up, Direction::Up);
             self.snake.push_front(Position(i, y));
         }
         self.snake_pos = Position(x, y);
-        self.board[x][y] = BrickType::SnakeHead(Direction::Up);
+        self[x][y] = BrickType::SnakeHead(Direction::Up);
     }
 
     fn create_wall_outline(&mut self) {
-        self[0].fill(BrickType::Wall);
-        self.board[self.x_size - 1].fill(BrickType::Wall);
+        self.terrain[0].fill(BrickType::Wall);
+        self.terrain[self.x_size - 1].fill(BrickType::Wall);
         for i in 1..self.x_size - 1 {
-            self.board[i][0] = BrickType::Wall;
-            self.board[i][self.y_size - 1] = BrickType::Wall;
+            self.terrain[i][0] = BrickType::Wall;
+            self.terrain[i][self.y_size - 1] = BrickType::Wall;
         };
     }
 
+    /// A random free cell for food. Open group walls are left out, since
+    /// food on them would keep them from closing.
     pub fn find_valid_food_spawn(&mut self) -> Option<Position> {
         let mut empty_bricks = Vec::<Position>::new();
-        for x in 0..self.board.len() {
-            for y in 0..self.board[x].len() {
-                if self.board[x][y] == BrickType::None {
-                    empty_bricks.push(Position(x, y));
+        for x in 0..self.x_size {
+            for y in 0..self.y_size {
+                let pos = Position(x, y);
+                if self.is_free(pos) && !matches!(self.get_terrain(pos), BrickType::GroupWall(..)) {
+                    empty_bricks.push(pos);
                 }
             }
         }
-        empty_bricks.choose(&mut rand::thread_rng()).cloned()
+        empty_bricks.choose(&mut self.food_rng).cloned()
+    }
+
+    /// Puts food on a free cell and returns where, if there was room.
+    pub fn place_food(&mut self) -> Option<Position> {
+        let pos = self.find_valid_food_spawn()?;
+        self[pos] = BrickType::Food;
+        Some(pos)
+    }
+
+    /// Seeds the food spawns and places the food already on the board anew
+    /// from the seed.
+    pub fn seed_food(&mut self, seed: u64) {
+        self.food_seed = seed;
+        self.food_rng = StdRng::seed_from_u64(seed);
+        let mut foods = 0;
+        for brick in self.occupants.iter_mut().flatten().filter(|brick| **brick == BrickType::Food) {
+            *brick = BrickType::None;
+            foods += 1;
+        }
+        for _ in 0..foods {
+            self.place_food();
+        }
+    }
+
+    /// Tops the food on the board up to the number the rules ask for.
+    pub fn fill_food(&mut self) {
+        let foods = self.occupants.iter().flatten().filter(|brick| **brick == BrickType::Food).count();
+        for _ in foods..self.rules.foods {
+            self.place_food();
+        }
+    }
+
+    pub fn find_doors(&self, colour: i16) -> Vec<Position> {
+        let mut doors = Vec::<Position>::new();
+        for x in 0..self.x_size {
+            for y in 0..self.y_size {
+                if self.terrain[x][y] == BrickType::Door(colour) {
+                    doors.push(Position(x, y));
+                }
+            }
+        }
+        doors
+    }
+
+    pub fn get_wall_group(&mut self, name: &str) -> usize {
+        let id = self.wall_groups.iter().position(|group| group.name == name);
+        id.unwrap_or_else(|| {
+            self.wall_groups.push(WallGroup { name: name.to_owned(), cells: Vec::new(), solid: true });
+            self.wall_groups.len() - 1
+        })
+    }
+
+    pub fn get_terrain(&self, Position(x, y): Position) -> &BrickType {
+        &self.terrain[x][y]
+    }
+
+    /// The adjacent cell in `dir`, or None when it lies outside the board.
+    pub fn neighbour(&self, Position(x, y): Position, dir: Direction) -> Option<Position> {
+        match dir {
+            Direction::Up    => x.checked_sub(1).map(|x| Position(x, y)),
+            Direction::Left  => y.checked_sub(1).map(|y| Position(x, y)),
+            Direction::Down  => (x + 1 < self.x_size).then(|| Position(x + 1, y)),
+            Direction::Right => (y + 1 < self.y_size).then(|| Position(x, y + 1)),
+        }
+    }
+
+    /// The cell the snake reaches by moving from `pos` in `dir`, wrapping
+    /// around the edges when the rules allow it.
+    pub fn step_from(&self, pos: Position, dir: Direction) -> Option<Position> {
+        if let Some(next) = self.neighbour(pos, dir) {
+            return Some(next);
+        }
+        if !self.rules.wrap_around {
+            return None;
+        }
+        let Position(x, y) = pos;
+        Some(match dir {
+            Direction::Up    => Position(self.x_size - 1, y),
+            Direction::Down  => Position(0, y),
+            Direction::Left  => Position(x, self.y_size - 1),
+            Direction::Right => Position(x, 0),
+        })
+    }
+
+    pub fn set_terrain(&mut self, Position(x, y): Position, brick: BrickType) {
+        self.terrain[x][y] = brick;
+    }
+
+    pub fn is_free(&self, pos: Position) -> bool {
+        self[pos] == BrickType::None && self.get_terrain(pos).is_floor()
+    }
+
+    pub fn get_visible(&self, pos: Position) -> &BrickType {
+        match self[pos] {
+            BrickType::None => self.get_terrain(pos),
+            ref brick => brick,
+        }
+    }
+
+    pub fn score(&self) -> usize {
+        self.food_eaten
     }
 
     pub fn get_head(&self) -> Position {
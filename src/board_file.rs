@@ -1,6 +1,15 @@
 use crate::board::*;
+use crate::board_metadata::BoardMetadata;
+use crate::grid_file;
 use std::{fs::File, io::BufRead};
 use std::io::BufReader;
+use std::fmt::Write;
+
+/// Reads the colour of a portal, key or door, one of the eight basic
+/// colours.
+pub fn parse_colour(s: &str) -> Option<i16> {
+    s.parse().ok().filter(|colour| (0..8).contains(colour))
+}
 
 #[derive(Copy, Clone)]
 struct SimpleBar {
@@ -23,7 +32,8 @@ impl SimpleBar {
 }
 
 struct WallBar {
-    base: SimpleBar
+    base: SimpleBar,
+    group: Option<String>,
 }
 
 impl WallBar {
@@ -31,13 +41,21 @@ impl WallBar {
     where
         I: Iterator<Item = &'a str>
     {
-        Some(WallBar { base: SimpleBar::from_line(iter)? })
+        let basic_bar = SimpleBar::from_line(iter)?;
+        Some(WallBar { base: basic_bar, group: iter.next().map(String::from) })
     }
 
     pub fn apply(&self, board: &mut Board) {
+        let group = self.group.as_ref().map(|name| board.get_wall_group(name));
         let mut pos = self.base.pos;
         for _ in 0..self.base.len {
-            board[pos] = BrickType::Wall;
+            match group {
+                Some(id) => {
+                    board.set_terrain(pos, BrickType::GroupWall(id, true));
+                    board.wall_groups[id].cells.push(pos);
+                },
+                None => board.set_terrain(pos, BrickType::Wall),
+            }
             pos = pos.move_dir(self.base.dir);
         }
     }
@@ -58,7 +76,7 @@ impl SnakeBar {
     pub fn apply(&self, board: &mut Board) {
         let mut pos = self.base.pos;
         for _ in 0..self.base.len {
-            board[pos] = BrickType::Snake(self.base.dir);
+            board[pos] = BrickType::Snake(self.base.dir, self.base.dir);
             board.snake.push_front(pos);
             pos = pos.move_dir(self.base.dir);
         }
@@ -85,7 +103,7 @@ impl PortalBar {
         let y: usize = iter.next()?.parse().ok()?;
         let rotation: u8 = iter.next()?.parse().ok()?;
         let mirror: u8 = iter.next()?.parse().ok()?;
-        let colour: i16 = iter.next()?.parse().ok()?;
+        let colour = parse_colour(iter.next()?)?;
 
         Some(PortalBar {
             base: basic_bar,
@@ -103,28 +121,236 @@ impl PortalBar {
         let dest_dir = self.base.dir.rotate(self.rotation);
         let out_rotation = if self.mirror { self.rotation + 2 } else { self.rotation };
         for _ in 0..self.base.len {
-            board[source_pos] = BrickType::Portal(
+            board.set_terrain(source_pos, BrickType::Portal(
                 Box::new(PortalData {
                     destination: dest_pos,
                     colour: self.colour,
                     rotation: out_rotation
                 })
-            );
+            ));
             source_pos = source_pos.move_dir(source_dir);
             dest_pos = dest_pos.move_dir(dest_dir);
         }
     }
 }
 
-struct BoardBuilder {
+struct GateBar {
+    base: SimpleBar,
+    allowed: Direction,
+}
+
+impl GateBar {
+    pub fn from_line<'a, I>(iter: &mut I) -> Option<GateBar> 
+    where
+        I: Iterator<Item = &'a str>
+    {
+        let basic_bar = SimpleBar::from_line(iter)?;
+        let allowed = Direction::from_primitive(iter.next()?.parse().ok()?);
+        Some(GateBar { base: basic_bar, allowed })
+    }
+
+    pub fn apply(&self, board: &mut Board) {
+        let mut pos = self.base.pos;
+        for _ in 0..self.base.len {
+            board.set_terrain(pos, BrickType::Gate(self.allowed));
+            pos = pos.move_dir(self.base.dir);
+        }
+    }
+}
+
+struct KeyBar {
+    base: SimpleBar,
+    colour: i16,
+}
+
+impl KeyBar {
+    pub fn from_line<'a, I>(iter: &mut I) -> Option<KeyBar> 
+    where
+        I: Iterator<Item = &'a str>
+    {
+        let basic_bar = SimpleBar::from_line(iter)?;
+        let colour = parse_colour(iter.next()?)?;
+        Some(KeyBar { base: basic_bar, colour })
+    }
+
+    pub fn apply(&self, board: &mut Board) {
+        let mut pos = self.base.pos;
+        for _ in 0..self.base.len {
+            board[pos] = BrickType::Key(self.colour);
+            pos = pos.move_dir(self.base.dir);
+        }
+    }
+}
+
+struct DoorBar {
+    base: SimpleBar,
+    colour: i16,
+}
+
+impl DoorBar {
+    pub fn from_line<'a, I>(iter: &mut I) -> Option<DoorBar> 
+    where
+        I: Iterator<Item = &'a str>
+    {
+        let basic_bar = SimpleBar::from_line(iter)?;
+        let colour = parse_colour(iter.next()?)?;
+        Some(DoorBar { base: basic_bar, colour })
+    }
+
+    pub fn apply(&self, board: &mut Board) {
+        let mut pos = self.base.pos;
+        for _ in 0..self.base.len {
+            board.set_terrain(pos, BrickType::Door(self.colour));
+            pos = pos.move_dir(self.base.dir);
+        }
+    }
+}
+
+struct IceBar {
+    base: SimpleBar
+}
+
+impl IceBar {
+    pub fn from_line<'a, I>(iter: &mut I) -> Option<IceBar> 
+    where
+        I: Iterator<Item = &'a str>
+    {
+        Some(IceBar { base: SimpleBar::from_line(iter)? })
+    }
+
+    pub fn apply(&self, board: &mut Board) {
+        let mut pos = self.base.pos;
+        for _ in 0..self.base.len {
+            board.set_terrain(pos, BrickType::Ice);
+            pos = pos.move_dir(self.base.dir);
+        }
+    }
+}
+
+struct ConveyorBar {
+    base: SimpleBar,
+    push: Direction,
+}
+
+impl ConveyorBar {
+    pub fn from_line<'a, I>(iter: &mut I) -> Option<ConveyorBar> 
+    where
+        I: Iterator<Item = &'a str>
+    {
+        let basic_bar = SimpleBar::from_line(iter)?;
+        let push = Direction::from_primitive(iter.next()?.parse().ok()?);
+        Some(ConveyorBar { base: basic_bar, push })
+    }
+
+    pub fn apply(&self, board: &mut Board) {
+        let mut pos = self.base.pos;
+        for _ in 0..self.base.len {
+            board.set_terrain(pos, BrickType::Conveyor(self.push));
+            pos = pos.move_dir(self.base.dir);
+        }
+    }
+}
+
+struct SwitchBar {
+    base: SimpleBar,
+    group: String,
+}
+
+impl SwitchBar {
+    pub fn from_line<'a, I>(iter: &mut I) -> Option<SwitchBar> 
+    where
+        I: Iterator<Item = &'a str>
+    {
+        let basic_bar = SimpleBar::from_line(iter)?;
+        let group = iter.next()?.to_owned();
+        Some(SwitchBar { base: basic_bar, group })
+    }
+
+    pub fn apply(&self, board: &mut Board) {
+        let id = board.get_wall_group(&self.group);
+        let mut pos = self.base.pos;
+        for _ in 0..self.base.len {
+            board.set_terrain(pos, BrickType::Switch(id));
+            pos = pos.move_dir(self.base.dir);
+        }
+    }
+}
+
+struct DynamicWallBar {
+    base: SimpleBar,
+    period: u64,
+    phase: u64,
+    motion: WallMotion,
+}
+
+impl DynamicWallBar {
+    pub fn timed_from_line<'a, I>(iter: &mut I) -> Option<DynamicWallBar> 
+    where
+        I: Iterator<Item = &'a str>
+    {
+        let basic_bar = SimpleBar::from_line(iter)?;
+        let period: u64 = iter.next()?.parse().ok()?;
+        let on: u64 = iter.next()?.parse().ok()?;
+        let phase: u64 = iter.next()?.parse().ok()?;
+        Self::new(basic_bar, period, phase, WallMotion::Timed { on })
+    }
+
+    pub fn moving_from_line<'a, I>(iter: &mut I) -> Option<DynamicWallBar> 
+    where
+        I: Iterator<Item = &'a str>
+    {
+        let basic_bar = SimpleBar::from_line(iter)?;
+        let dir = Direction::from_primitive(iter.next()?.parse().ok()?);
+        let distance: usize = iter.next()?.parse().ok()?;
+        let period: u64 = iter.next()?.parse().ok()?;
+        let phase: u64 = iter.next()?.parse().ok()?;
+        Self::new(basic_bar, period, phase, WallMotion::Moving { dir, distance })
+    }
+
+    fn new(base: SimpleBar, period: u64, phase: u64, motion: WallMotion) -> Option<DynamicWallBar> {
+        if period == 0 {
+            return None;
+        }
+        Some(DynamicWallBar { base, period, phase, motion })
+    }
+
+    /// Adds the wall, or gives None when it would leave the board at some
+    /// point of its motion.
+    pub fn apply(&self, board: &mut Board) -> Option<()> {
+        let mut wall = DynamicWall {
+            pos: self.base.pos,
+            len: self.base.len,
+            dir: self.base.dir,
+            period: self.period,
+            phase: self.phase,
+            motion: self.motion,
+            cells: Vec::new(),
+        };
+        if !wall.fits(board) {
+            return None;
+        }
+        wall.cells = wall.cells_at(board, board.tick);
+        wall.cells.retain(|pos| *board.get_terrain(*pos) == BrickType::None);
+        for pos in &wall.cells {
+            board.set_terrain(*pos, BrickType::DynamicWall);
+        }
+        board.dynamic_walls.push(wall);
+        Some(())
+    }
+}
+
+pub struct BoardBuilder {
     board: Board
 }
 
 impl BoardBuilder {
-    fn build(mut self) -> Board {
+    pub fn build(mut self) -> Board {
         if !self.board.snake.is_empty() {
             let head = *self.board.snake.front().unwrap();
             self.board.snake_pos = head;
+            if let BrickType::Snake(entered, _) = self.board[head] {
+                self.board.head_entered = entered;
+            }
             self.board[head] = BrickType::SnakeHead(self.board.facing);
         }
         let pos = self.board.find_valid_food_spawn().unwrap();
@@ -133,7 +359,7 @@ impl BoardBuilder {
         self.board
     }
 
-    fn add_bar(&mut self, line: String) -> Option<()> {
+    pub fn add_bar(&mut self, line: String) -> Option<()> {
         let mut iter = line.split_whitespace();
         let bg = iter.next()?;
         if bg.len() != 1 {
@@ -150,33 +376,438 @@ impl BoardBuilder {
             'P' => {
                 PortalBar::from_line(&mut iter)?.apply(&mut self.board);
             },
+            'G' => {
+                GateBar::from_line(&mut iter)?.apply(&mut self.board);
+            },
+            'K' => {
+                KeyBar::from_line(&mut iter)?.apply(&mut self.board);
+            },
+            'D' => {
+                DoorBar::from_line(&mut iter)?.apply(&mut self.board);
+            },
+            'I' => {
+                IceBar::from_line(&mut iter)?.apply(&mut self.board);
+            },
+            'C' => {
+                ConveyorBar::from_line(&mut iter)?.apply(&mut self.board);
+            },
+            'X' => {
+                SwitchBar::from_line(&mut iter)?.apply(&mut self.board);
+            },
+            'T' => {
+                DynamicWallBar::timed_from_line(&mut iter)?.apply(&mut self.board)?;
+            },
+            'M' => {
+                DynamicWallBar::moving_from_line(&mut iter)?.apply(&mut self.board)?;
+            },
             _ => {}
         };
         Some(())
     }
 
-    fn new(x_size: usize, y_size: usize) -> BoardBuilder {
+    pub fn new(x_size: usize, y_size: usize) -> BoardBuilder {
         BoardBuilder { board: Board::new_empty(x_size, y_size) }
     }
+
+    pub fn board(&mut self) -> &mut Board {
+        &mut self.board
+    }
 }
 
-pub fn from_file(file_path: &str) -> Option<Board> {
+/// A line of portal cells whose destinations form a line as well, so it
+/// can be written back as a single `P` bar.
+pub struct PortalRun {
+    pub start: Position,
+    pub len: usize,
+    pub dir: Direction,
+    pub destination: Position,
+    pub rotation: u8,
+    pub mirror: bool,
+    pub colour: i16,
+}
+
+impl PortalRun {
+    pub fn cells(&self) -> Vec<Position> {
+        Self::line(self.start, self.dir, self.len)
+    }
+
+    pub fn destinations(&self) -> Vec<Position> {
+        Self::line(self.destination, self.dir.rotate(self.rotation), self.len)
+    }
+
+    fn line(start: Position, dir: Direction, len: usize) -> Vec<Position> {
+        let mut pos = start;
+        let mut cells = vec![pos];
+        for _ in 1..len {
+            pos = pos.move_dir(dir);
+            cells.push(pos);
+        }
+        cells
+    }
+}
+
+fn portal_at(board: &Board, pos: Position) -> Option<&PortalData> {
+    match board.get_terrain(pos) {
+        BrickType::Portal(data) => Some(data),
+        _ => None
+    }
+}
+
+fn direction_between(from: Position, to: Position) -> Option<Direction> {
+    Direction::ALL.into_iter()
+        .find(|dir| match (from, *dir) {
+            (Position(0, _), Direction::Up) | (Position(_, 0), Direction::Left) => false,
+            _ => from.move_dir(*dir) == to,
+        })
+}
+
+/// Groups the board's portals into runs going right or down from their
+/// first cell in reading order.
+pub fn portal_runs(board: &Board) -> Vec<PortalRun> {
+    let mut used = vec![vec![false; board.y_size()]; board.x_size()];
+    let mut runs = Vec::new();
+    for x in 0..board.x_size() {
+        for y in 0..board.y_size() {
+            let start = Position(x, y);
+            let data = match portal_at(board, start) {
+                Some(data) if !used[x][y] => data,
+                _ => continue,
+            };
+            let mut run = PortalRun {
+                start,
+                len: 1,
+                dir: Direction::Right,
+                destination: data.destination,
+                rotation: data.rotation,
+                mirror: false,
+                colour: data.colour,
+            };
+            for dir in [Direction::Right, Direction::Down] {
+                let next = match board.neighbour(start, dir) {
+                    Some(next) => next,
+                    None => continue,
+                };
+                let dest_dir = match portal_at(board, next) {
+                    Some(next_data) if next_data.colour == data.colour && next_data.rotation == data.rotation => {
+                        direction_between(data.destination, next_data.destination)
+                    },
+                    _ => None,
+                };
+                let dest_dir = match dest_dir {
+                    Some(dest_dir) => dest_dir,
+                    None => continue,
+                };
+                let rotation = (dest_dir as u8 + 4 - dir as u8) % 4;
+                let mirror = match (data.rotation + 4 - rotation) % 4 {
+                    0 => false,
+                    2 => true,
+                    _ => continue,
+                };
+                run.dir = dir;
+                run.rotation = rotation;
+                run.mirror = mirror;
+                let (mut pos, mut dest) = (start, data.destination);
+                loop {
+                    let next = board.neighbour(pos, dir);
+                    let next_dest = board.neighbour(dest, dest_dir);
+                    let extends = match (next, next_dest) {
+                        (Some(next), Some(next_dest)) => !used[next.0][next.1] && portal_at(board, next).is_some_and(|next_data| {
+                            next_data.colour == data.colour && next_data.rotation == data.rotation && next_data.destination == next_dest
+                        }),
+                        _ => false,
+                    };
+                    if !extends {
+                        break;
+                    }
+                    (pos, dest) = (next.unwrap(), next_dest.unwrap());
+                    run.len += 1;
+                }
+                break;
+            }
+            for Position(cx, cy) in run.cells() {
+                used[cx][cy] = true;
+            }
+            runs.push(run);
+        }
+    }
+    runs
+}
+
+/// Describes a brick as the type letter of a one-cell bar and the
+/// arguments that follow the bar's direction. Bricks that have no bar of
+/// their own, or that are written separately, give None.
+fn bar_args(board: &Board, brick: &BrickType) -> Option<(char, String)> {
+    match brick {
+        BrickType::Wall => Some(('W', String::new())),
+        BrickType::GroupWall(id, _) => Some(('W', format!(" {}", board.wall_groups[*id].name))),
+        BrickType::Gate(dir) => Some(('G', format!(" {}", *dir as u8))),
+        BrickType::Key(colour) => Some(('K', format!(" {}", colour))),
+        BrickType::Door(colour) => Some(('D', format!(" {}", colour))),
+        BrickType::Ice => Some(('I', String::new())),
+        BrickType::Conveyor(dir) => Some(('C', format!(" {}", *dir as u8))),
+        BrickType::Switch(id) => Some(('X', format!(" {}", board.wall_groups[*id].name))),
+        _ => None
+    }
+}
+
+/// Writes one bar line per run of equal bricks in both layers, going
+/// right or down from each cell not covered yet, whichever run is longer.
+/// Bricks for which `skip` returns true are left out.
+pub fn write_bar_runs(board: &Board, out: &mut String, skip: impl Fn(&BrickType) -> bool) {
+    for layer in [&board.terrain, &board.occupants] {
+        let mut covered = vec![vec![false; board.y_size()]; board.x_size()];
+        for x in 0..board.x_size() {
+            for y in 0..board.y_size() {
+                let brick = &layer[x][y];
+                let (letter, args) = match bar_args(board, brick) {
+                    Some(args) if !covered[x][y] && !skip(brick) => args,
+                    _ => continue,
+                };
+                let run_length = |dir: Direction| {
+                    let mut pos = Position(x, y);
+                    let mut len = 1;
+                    while let Some(next) = board.neighbour(pos, dir) {
+                        if covered[next.0][next.1] || layer[next.0][next.1] != *brick {
+                            break;
+                        }
+                        pos = next;
+                        len += 1;
+                    }
+                    len
+                };
+                let (right, down) = (run_length(Direction::Right), run_length(Direction::Down));
+                let (dir, len) = if down > right { (Direction::Down, down) } else { (Direction::Right, right) };
+                let mut pos = Position(x, y);
+                for _ in 0..len {
+                    covered[pos.0][pos.1] = true;
+                    pos = pos.move_dir(dir);
+                }
+                let _ = writeln!(out, "{} {} {} {} {}{}", letter, x, y, len, dir as u8, args);
+            }
+        }
+    }
+}
+
+pub fn write_dynamic_walls(board: &Board, out: &mut String) {
+    for wall in &board.dynamic_walls {
+        let Position(x, y) = wall.pos;
+        let phase = wall.phase + board.tick;
+        let _ = match wall.motion {
+            WallMotion::Timed { on } => writeln!(out, "T {} {} {} {} {} {} {}",
+                x, y, wall.len, wall.dir as u8, wall.period, on, phase),
+            WallMotion::Moving { dir, distance } => writeln!(out, "M {} {} {} {} {} {} {} {}",
+                x, y, wall.len, wall.dir as u8, dir as u8, distance, wall.period, phase),
+        };
+    }
+}
+
+/// The snake as a single `S` bar, which requires it to lie in a straight
+/// line facing the way it points.
+fn snake_bar(board: &Board) -> Result<String, String> {
+    let tail = match board.snake.back() {
+        Some(tail) => *tail,
+        None => return Ok(String::new()),
+    };
+    let dir = board.facing;
+    let mut pos = tail;
+    for segment in board.snake.iter().rev().skip(1) {
+        if board.neighbour(pos, dir) != Some(*segment) {
+            return Err("the snake has to be straight to be written as a bar".to_owned());
+        }
+        pos = *segment;
+    }
+    Ok(format!("S {} {} {} {}\n", tail.0, tail.1, board.snake.len(), dir as u8))
+}
+
+/// Writes the board's layout in the bar format read by `from_file`. Food
+/// is left out since it is placed anew when the board is loaded.
+pub fn to_string(board: &Board) -> Result<String, String> {
+    Ok(layout_to_string(board) + &snake_bar(board)?)
+}
+
+/// The board in the bar format without its snake, which can be bent in
+/// ways a bar cannot describe.
+pub fn layout_to_string(board: &Board) -> String {
+    let mut out = board.metadata.to_header();
+    let _ = writeln!(out, "{} {}", board.x_size(), board.y_size());
+    write_bar_runs(board, &mut out, |_| false);
+    for run in portal_runs(board) {
+        let Position(x, y) = run.start;
+        let Position(dx, dy) = run.destination;
+        let _ = writeln!(out, "P {} {} {} {} {} {} {} {} {}",
+            x, y, run.len, run.dir as u8, dx, dy, run.rotation, run.mirror as u8, run.colour);
+    }
+    write_dynamic_walls(board, &mut out);
+    out
+}
+
+fn read_lines(file_path: &str) -> Option<Vec<String>> {
     let file = File::open(file_path).ok()?;
-    let reader = BufReader::new(file);
-    let mut lines_iter = reader.lines();
+    BufReader::new(file).lines().collect::<Result<_, _>>().ok()
+}
+
+pub fn read_metadata(file_path: &str) -> Option<BoardMetadata> {
+    Some(BoardMetadata::parse(&read_lines(file_path)?).0)
+}
+
+pub fn from_file(file_path: &str) -> Option<Board> {
+    let lines = read_lines(file_path)?;
+    let (metadata, header_len) = BoardMetadata::parse(&lines);
+    let lines = &lines[header_len..];
+    let mut board = if file_path.ends_with(".grid") || grid_file::has_header(lines) {
+        grid_file::from_lines(lines)?
+    } else {
+        from_lines(lines)?
+    };
+    board.metadata = metadata;
+    Some(board)
+}
 
-    let line = lines_iter.next()?.ok()?;
+fn from_lines(lines: &[String]) -> Option<Board> {
+    let mut lines_iter = lines.iter();
+
+    let line = lines_iter.next()?;
     let mut iter = line.split_whitespace();
     let x_size: usize = iter.next()?.parse().ok()?;
     let y_size: usize = iter.next()?.parse().ok()?;
 
     let mut builder = BoardBuilder::new(x_size, y_size);
     for line in lines_iter {
-        let line = line.ok()?;
         if line.is_empty() {
             continue;
         }
-        builder.add_bar(line);
+        builder.add_bar(line.clone());
     }
     Some(builder.build())
 }
+
+/// Converts a board file between the bar and grid formats, picking the
+/// output format from the extension of `output`.
+pub fn convert(input: &str, output: &str) -> Result<(), String> {
+    let board = from_file(input).ok_or(format!("could not read board {}", input))?;
+    for warning in &board.metadata.warnings {
+        println!("Warning: {}: {}", input, warning);
+    }
+    let content = if output.ends_with(".grid") {
+        grid_file::to_string(&board)?
+    } else {
+        to_string(&board)?
+    };
+    std::fs::write(output, content).map_err(|err| format!("could not write {}: {}", output, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board with one of every kind of bar.
+    const EVERY_BAR: &str = "\
+10 12
+W 0 0 12 1
+W 9 0 12 1
+W 1 0 8 2
+W 1 11 8 2
+G 2 2 3 1 1
+K 4 2 1 1 2
+D 4 4 2 2 2
+I 6 2 3 1
+C 7 2 2 1 3
+W 2 8 3 2 bridge
+X 7 6 1 1 bridge
+T 3 6 1 1 4 2 0
+M 5 6 2 1 1 2 3 1
+S 8 5 3 1";
+
+    fn to_lines(content: &str) -> Vec<String> {
+        content.lines().map(String::from).collect()
+    }
+
+    /// Reads a board back from what `to_string` or `grid_file::to_string`
+    /// wrote, the way `from_file` does.
+    fn reread(content: &str) -> Board {
+        let lines = to_lines(content);
+        let (metadata, header_len) = BoardMetadata::parse(&lines);
+        let lines = &lines[header_len..];
+        let mut board = if grid_file::has_header(lines) {
+            grid_file::from_lines(lines)
+        } else {
+            from_lines(lines)
+        }.expect("written boards can be read back");
+        board.metadata = metadata;
+        board
+    }
+
+    /// Writes a board as bars, then as a grid, then as bars again.
+    fn assert_round_trip(board: &Board, name: &str) {
+        let bars = to_string(board).unwrap();
+        let grid = grid_file::to_string(&reread(&bars)).unwrap();
+        assert_eq!(to_string(&reread(&grid)).unwrap(), bars, "{} changed through the grid format", name);
+    }
+
+    #[test]
+    fn shipped_boards_round_trip() {
+        for entry in std::fs::read_dir("boards").unwrap() {
+            let path = entry.unwrap().path();
+            let path = path.to_str().unwrap();
+            assert_round_trip(&from_file(path).unwrap(), path);
+        }
+    }
+
+    #[test]
+    fn every_bar_round_trips() {
+        let board = from_lines(&to_lines(EVERY_BAR)).unwrap();
+        assert_eq!(board.dynamic_walls.len(), 2);
+        assert_eq!(board.wall_groups.len(), 1);
+        let bars = to_string(&board).unwrap();
+        for letter in ['W', 'G', 'K', 'D', 'I', 'C', 'X', 'T', 'M', 'S'] {
+            assert!(bars.lines().any(|line| line.starts_with(letter)), "no {} bar was read", letter);
+        }
+        assert_round_trip(&board, "every bar");
+    }
+
+    #[test]
+    fn portal_runs_join_portal_lines() {
+        let board = from_file("boards/simple_portal.board").unwrap();
+        let runs = portal_runs(&board);
+        assert_eq!(runs.len(), 2);
+        for run in &runs {
+            assert_eq!(run.len, 13);
+            assert!(run.dir == Direction::Down);
+            assert_eq!(run.colour, 3);
+        }
+        assert!(runs[0].destinations() == runs[1].cells());
+    }
+
+    #[test]
+    fn shipped_portals_lead_onto_floor() {
+        for entry in std::fs::read_dir("boards").unwrap() {
+            let path = entry.unwrap().path();
+            let board = from_file(path.to_str().unwrap()).unwrap();
+            for x in 0..board.x_size() {
+                for y in 0..board.y_size() {
+                    let data = match board.get_terrain(Position(x, y)) {
+                        BrickType::Portal(data) => data,
+                        _ => continue,
+                    };
+                    for dir in Direction::ALL {
+                        let entered = board.neighbour(Position(x, y), dir.mirror())
+                            .is_some_and(|from| board.get_terrain(from).is_floor());
+                        let landing = board.neighbour(data.destination, dir.rotate(data.rotation));
+                        assert!(!entered || landing.is_some_and(|pos| board.get_terrain(pos).is_floor()),
+                            "{}: entering ({}, {}) going {} hits a wall", path.display(), x, y, dir as u8);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn colours_outside_the_basic_eight_are_rejected() {
+        assert_eq!(parse_colour("7"), Some(7));
+        assert_eq!(parse_colour("8"), None);
+        assert_eq!(parse_colour("-1"), None);
+        let mut builder = BoardBuilder::new(5, 5);
+        assert!(builder.add_bar("K 1 1 1 1 8".to_owned()).is_none());
+        assert!(builder.add_bar("K 1 1 1 1 2".to_owned()).is_some());
+    }
+}
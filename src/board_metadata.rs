@@ -0,0 +1,130 @@
+use std::fmt::Write;
+
+use crate::rules::RuleOverrides;
+
+pub const FORMAT_VERSION: u32 = 1;
+const DELIMITER: &str = "---";
+
+// The metadata block is optional and sits above the rest of a board file:
+//
+//   ---
+//   name: U pattern
+//   author: someone
+//   description: Go around the middle wall
+//   difficulty: 2
+//   speed: 250
+//   tags: walls, beginner
+//   version: 1
+//   growth: 2             any game rule, overriding the global config
+//   ---
+
+#[derive(Clone, Default)]
+pub struct BoardMetadata {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub difficulty: Option<u8>,
+    /// Recommended milliseconds per tick.
+    pub speed: Option<u64>,
+    pub tags: Vec<String>,
+    pub version: Option<u32>,
+    pub rules: RuleOverrides,
+    pub warnings: Vec<String>,
+}
+
+impl BoardMetadata {
+    /// Reads the metadata block at the top of `lines`, if there is one.
+    /// Returns the metadata and the number of lines it took up.
+    pub fn parse(lines: &[String]) -> (BoardMetadata, usize) {
+        let mut metadata = BoardMetadata::default();
+        if lines.first().map(|line| line.trim()) != Some(DELIMITER) {
+            return (metadata, 0);
+        }
+        for (i, line) in lines.iter().enumerate().skip(1) {
+            let line = line.trim();
+            if line == DELIMITER {
+                return (metadata, i + 1);
+            }
+            if line.is_empty() {
+                continue;
+            }
+            match line.split_once(':') {
+                Some((key, value)) => metadata.set(i + 1, key.trim(), value.trim()),
+                None => metadata.warn(i + 1, format!("expected 'key: value', got '{}'", line)),
+            }
+        }
+        metadata.warn(1, "the metadata block is never closed".to_owned());
+        (metadata, lines.len())
+    }
+
+    fn warn(&mut self, line: usize, message: String) {
+        self.warnings.push(format!("line {}: {}", line, message));
+    }
+
+    fn set(&mut self, line: usize, key: &str, value: &str) {
+        match key {
+            "name" => self.name = Some(value.to_owned()),
+            "author" => self.author = Some(value.to_owned()),
+            "description" => self.description = Some(value.to_owned()),
+            "difficulty" => self.difficulty = self.parse_number(line, key, value),
+            "speed" => {
+                self.speed = self.parse_number(line, key, value);
+                if self.speed == Some(0) {
+                    self.speed = None;
+                    self.warn(line, "speed has to be above 0".to_owned());
+                }
+            },
+            "tags" => {
+                self.tags = value.split(',').map(|tag| tag.trim().to_owned()).filter(|tag| !tag.is_empty()).collect();
+            },
+            "version" => {
+                self.version = self.parse_number(line, key, value);
+                if self.version.is_some_and(|version| version > FORMAT_VERSION) {
+                    self.warn(line, format!("format version {} is newer than {}", value, FORMAT_VERSION));
+                }
+            },
+            _ => match self.rules.set(key, value) {
+                Some(true) => {},
+                Some(false) => self.warn(line, format!("'{}' is not a valid {}", value, key)),
+                None => self.warn(line, format!("unknown key '{}'", key)),
+            },
+        }
+    }
+
+    fn parse_number<T: std::str::FromStr>(&mut self, line: usize, key: &str, value: &str) -> Option<T> {
+        let number = value.parse().ok();
+        if number.is_none() {
+            self.warn(line, format!("'{}' is not a valid {}", value, key));
+        }
+        number
+    }
+
+    /// Writes the block back, or nothing when no field is set.
+    pub fn to_header(&self) -> String {
+        let mut fields = String::new();
+        let text_fields = [("name", &self.name), ("author", &self.author), ("description", &self.description)];
+        for (key, value) in text_fields {
+            if let Some(value) = value {
+                let _ = writeln!(fields, "{}: {}", key, value);
+            }
+        }
+        if let Some(difficulty) = self.difficulty {
+            let _ = writeln!(fields, "difficulty: {}", difficulty);
+        }
+        if let Some(speed) = self.speed {
+            let _ = writeln!(fields, "speed: {}", speed);
+        }
+        if !self.tags.is_empty() {
+            let _ = writeln!(fields, "tags: {}", self.tags.join(", "));
+        }
+        if let Some(version) = self.version {
+            let _ = writeln!(fields, "version: {}", version);
+        }
+        self.rules.write(&mut fields);
+        if fields.is_empty() {
+            fields
+        } else {
+            format!("{}\n{}{}\n", DELIMITER, fields, DELIMITER)
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::board::Position;
+
+/// Margin kept between the head and the edge of the view, as a fraction
+/// of the view's size.
+const DEAD_ZONE_DIVISOR: usize = 4;
+
+/// The part of the board shown on screen. It follows the snake's head
+/// once the head gets within the dead zone margin of the view's edge.
+pub struct Camera {
+    pub origin: Position,
+    pub height: usize,
+    pub width: usize,
+    /// Board rows drawn on every line of the screen.
+    pub rows_per_line: usize,
+    /// Terminal columns every board cell takes up.
+    pub columns_per_cell: usize,
+    board_height: usize,
+    board_width: usize,
+}
+
+/// The start of a view of `view` cells along an axis of `size` cells
+/// that keeps `head` at least `margin` cells from either edge.
+fn follow_axis(start: usize, view: usize, size: usize, head: usize, margin: usize) -> usize {
+    let start = if head < start + margin {
+        head.saturating_sub(margin)
+    } else if head + margin >= start + view {
+        head + margin + 1 - view
+    } else {
+        start
+    };
+    start.min(size.saturating_sub(view))
+}
+
+impl Camera {
+    pub fn new(board_height: usize, board_width: usize) -> Camera {
+        Camera {
+            origin: Position(0, 0),
+            height: board_height,
+            width: board_width,
+            rows_per_line: 1,
+            columns_per_cell: 1,
+            board_height,
+            board_width,
+        }
+    }
+
+    /// Fits the view to at most `lines` by `columns` characters.
+    pub fn resize(&mut self, lines: usize, columns: usize) {
+        self.height = (lines * self.rows_per_line).min(self.board_height);
+        self.width = (columns / self.columns_per_cell).min(self.board_width);
+    }
+
+    /// The screen lines the view takes up.
+    pub fn lines(&self) -> usize {
+        self.height.div_ceil(self.rows_per_line)
+    }
+
+    /// The terminal columns the view takes up.
+    pub fn columns(&self) -> usize {
+        self.width * self.columns_per_cell
+    }
+
+    /// Moves the view so `head` is outside the dead zone. Returns whether
+    /// the view moved.
+    pub fn follow(&mut self, Position(x, y): Position) -> bool {
+        let Position(x0, y0) = self.origin;
+        let origin = Position(
+            follow_axis(x0, self.height, self.board_height, x, self.height / DEAD_ZONE_DIVISOR),
+            follow_axis(y0, self.width, self.board_width, y, self.width / DEAD_ZONE_DIVISOR),
+        );
+        let moved = origin != self.origin;
+        self.origin = origin;
+        moved
+    }
+
+    pub fn is_scrolling(&self) -> bool {
+        self.height < self.board_height || self.width < self.board_width
+    }
+
+    /// The board cell shown at the given view coordinates.
+    pub fn to_board(&self, x: usize, y: usize) -> Position {
+        Position(self.origin.0 + x, self.origin.1 + y)
+    }
+
+    /// The screen line and the column of the view a board cell is drawn
+    /// at, if it is in view.
+    pub fn to_view(&self, Position(x, y): Position) -> Option<(usize, usize)> {
+        let Position(x0, y0) = self.origin;
+        if x < x0 || y < y0 || x >= x0 + self.height || y >= y0 + self.width {
+            return None;
+        }
+        Some(((x - x0) / self.rows_per_line, y - y0))
+    }
+
+    /// Scales the whole board down to `height` by `width` characters and
+    /// marks the ones that overlap the view.
+    pub fn minimap(&self, height: usize, width: usize) -> Vec<Vec<bool>> {
+        let Position(x0, y0) = self.origin;
+        (0..height).map(|row| {
+            let rows = (row * self.board_height / height, ((row + 1) * self.board_height).div_ceil(height));
+            (0..width).map(|col| {
+                let cols = (col * self.board_width / width, ((col + 1) * self.board_width).div_ceil(width));
+                rows.0 < x0 + self.height && x0 < rows.1 && cols.0 < y0 + self.width && y0 < cols.1
+            }).collect()
+        }).collect()
+    }
+}
@@ -0,0 +1,121 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::board::Board;
+
+const DEFAULT_LIVES: u32 = 3;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    Food(usize),
+    Length(usize),
+}
+
+impl Target {
+    fn from_line<'a, I>(iter: &mut I) -> Option<Target>
+    where
+        I: Iterator<Item = &'a str>
+    {
+        let kind = iter.next()?;
+        let value: usize = iter.next()?.parse().ok()?;
+        match kind {
+            "food" => Some(Target::Food(value)),
+            "length" => Some(Target::Length(value)),
+            _ => None
+        }
+    }
+
+    pub fn reached(&self, board: &Board) -> bool {
+        match *self {
+            Target::Food(count) => board.score() >= count,
+            Target::Length(len) => board.snake.len() >= len,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match *self {
+            Target::Food(count) => format!("eat {}", count),
+            Target::Length(len) => format!("grow to {}", len),
+        }
+    }
+}
+
+pub struct Level {
+    pub board: String,
+    pub target: Target,
+}
+
+pub struct Campaign {
+    pub name: String,
+    pub lives: u32,
+    pub levels: Vec<Level>,
+}
+
+impl Campaign {
+    /// Reads a campaign file: an optional `lives N` line, with N at least
+    /// 1, followed by one `<board> <food|length> <value>` line per level.
+    pub fn from_file(name: &str) -> Option<Campaign> {
+        let file = File::open(format!("campaigns/{}.campaign", name)).ok()?;
+        let mut campaign = Campaign { name: name.to_owned(), lives: DEFAULT_LIVES, levels: Vec::new() };
+        for line in BufReader::new(file).lines() {
+            let line = line.ok()?;
+            let mut iter = line.split_whitespace();
+            match iter.next() {
+                None => continue,
+                Some("lives") => {
+                    campaign.lives = iter.next()?.parse().ok().filter(|lives| *lives >= 1)?;
+                },
+                Some(board) => {
+                    let target = Target::from_line(&mut iter)?;
+                    campaign.levels.push(Level { board: board.to_owned(), target });
+                }
+            }
+        }
+        if campaign.levels.is_empty() {
+            None
+        } else {
+            Some(campaign)
+        }
+    }
+
+    fn progress_path(&self) -> String {
+        format!("saves/{}.progress", self.name)
+    }
+
+    /// Progress for a new run with the first `unlocked` + 1 levels open.
+    pub fn fresh_progress(&self, unlocked: usize) -> Progress {
+        Progress { unlocked, score: 0, lives: self.lives }
+    }
+
+    pub fn load_progress(&self) -> Progress {
+        let fresh = self.fresh_progress(0);
+        let content = match fs::read_to_string(self.progress_path()) {
+            Ok(content) => content,
+            Err(_) => return fresh,
+        };
+        let mut iter = content.split_whitespace().map(|s| s.parse::<usize>().ok());
+        match (iter.next().flatten(), iter.next().flatten(), iter.next().flatten()) {
+            (Some(unlocked), Some(score), Some(lives)) if lives >= 1 => Progress {
+                unlocked: unlocked.min(self.levels.len() - 1),
+                score,
+                lives: lives as u32,
+            },
+            _ => fresh
+        }
+    }
+
+    pub fn save_progress(&self, progress: &Progress) {
+        let path = self.progress_path();
+        if let Some(dir) = Path::new(&path).parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(path, format!("{} {} {}\n", progress.unlocked, progress.score, progress.lives));
+    }
+}
+
+pub struct Progress {
+    pub unlocked: usize,
+    pub score: usize,
+    pub lives: u32,
+}
@@ -0,0 +1,196 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use crate::basic_window::ColorPair;
+
+/// The levels of the 6x6x6 colour cube of 256 colour terminals.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Pair numbers that fit in the colour bits of a chtype. COLOR_PAIR moves
+/// higher ones into the bits of other attributes.
+const MAX_PAIRS: i32 = 256;
+
+const BASIC_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+    (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+    (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Colour {
+    /// One of the terminal's colours: 0 to 7 are the basic ones, up to
+    /// 255 on terminals with 256 colours.
+    Index(i16),
+    Rgb(u8, u8, u8),
+}
+
+impl Colour {
+    /// Reads a basic colour name, a colour number or `#rrggbb`.
+    pub fn parse(s: &str) -> Option<Colour> {
+        let basic = match s {
+            "black" => ncurses::COLOR_BLACK,
+            "red" => ncurses::COLOR_RED,
+            "green" => ncurses::COLOR_GREEN,
+            "yellow" => ncurses::COLOR_YELLOW,
+            "blue" => ncurses::COLOR_BLUE,
+            "magenta" => ncurses::COLOR_MAGENTA,
+            "cyan" => ncurses::COLOR_CYAN,
+            "white" => ncurses::COLOR_WHITE,
+            _ => {
+                if let Some(hex) = s.strip_prefix('#').filter(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit())) {
+                    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+                    return Some(Colour::Rgb(channel(0)?, channel(2)?, channel(4)?));
+                }
+                return s.parse().ok().filter(|index| (0..256).contains(index)).map(Colour::Index);
+            }
+        };
+        Some(Colour::Index(basic))
+    }
+}
+
+/// The usual RGB value of a colour of the 256 colour palette.
+fn palette_rgb(index: i16) -> (u8, u8, u8) {
+    match index {
+        0..=15 => BASIC_RGB[index as usize],
+        16..=231 => {
+            let i = index as usize - 16;
+            (CUBE_LEVELS[i / 36], CUBE_LEVELS[i / 6 % 6], CUBE_LEVELS[i % 6])
+        },
+        _ => {
+            let level = 8 + 10 * (index.clamp(232, 255) - 232) as u8;
+            (level, level, level)
+        },
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> i32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// Hands out colour pairs, reusing the one made for a foreground and
+/// background before. Colours the terminal lacks are defined with
+/// `init_color` where it allows that, or replaced with the closest one
+/// it has.
+pub struct ColourManager {
+    pairs: RefCell<HashMap<(i16, i16), ColorPair>>,
+    /// Terminal colours redefined to an RGB value.
+    defined: RefCell<HashMap<i16, (u8, u8, u8)>>,
+    /// Terminal colours handed out as they are, which are never redefined.
+    shown: RefCell<HashSet<i16>>,
+}
+
+impl ColourManager {
+    pub fn new() -> ColourManager {
+        ColourManager {
+            pairs: RefCell::new(HashMap::new()),
+            defined: RefCell::new(HashMap::new()),
+            shown: RefCell::new(HashSet::new()),
+        }
+    }
+
+    pub fn has_colour(&self) -> bool {
+        ncurses::has_colors() && ncurses::COLORS() >= 8
+    }
+
+    fn colours(&self) -> i16 {
+        ncurses::COLORS().min(256) as i16
+    }
+
+    /// The RGB value terminal colour `index` currently shows.
+    fn current_rgb(&self, index: i16) -> (u8, u8, u8) {
+        self.defined.borrow().get(&index).copied().unwrap_or_else(|| palette_rgb(index))
+    }
+
+    fn is_defined(&self, index: i16) -> bool {
+        self.defined.borrow().contains_key(&index)
+    }
+
+    /// The terminal colour closest to `rgb`.
+    fn nearest(&self, rgb: (u8, u8, u8)) -> i16 {
+        (0..self.colours()).min_by_key(|index| distance(self.current_rgb(*index), rgb)).unwrap_or(0)
+    }
+
+    /// Redefines the last terminal colour not in use as `rgb`. Only the
+    /// top of 256 colour palettes is given up, never the basic colours.
+    fn define(&self, rgb: (u8, u8, u8)) -> Option<i16> {
+        if let Some((index, _)) = self.defined.borrow().iter().find(|(_, defined)| **defined == rgb) {
+            return Some(*index);
+        }
+        if !ncurses::can_change_color() {
+            return None;
+        }
+        let shown = self.shown.borrow();
+        let index = (16..self.colours()).rev().find(|index| !self.is_defined(*index) && !shown.contains(index))?;
+        let scale = |c: u8| (c as i32 * 1000 / 255) as i16;
+        ncurses::init_color(index, scale(rgb.0), scale(rgb.1), scale(rgb.2));
+        self.defined.borrow_mut().insert(index, rgb);
+        Some(index)
+    }
+
+    /// The terminal colour `colour` is shown in. Colour numbers taken over
+    /// by `define` are replaced with the closest colour, like those the
+    /// terminal lacks.
+    pub fn resolve(&self, colour: Colour) -> i16 {
+        let index = match colour {
+            Colour::Index(index) if index < self.colours() && !self.is_defined(index) => index,
+            Colour::Index(index) => self.nearest(palette_rgb(index)),
+            Colour::Rgb(r, g, b) => self.define((r, g, b)).unwrap_or_else(|| self.nearest((r, g, b))),
+        };
+        if !self.is_defined(index) {
+            self.shown.borrow_mut().insert(index);
+        }
+        index
+    }
+
+    /// A pair for `fg` on `bg`, or None when the terminal has no pairs left.
+    pub fn pair(&self, fg: Colour, bg: Colour) -> Option<ColorPair> {
+        let key = (self.resolve(fg), self.resolve(bg));
+        let mut pairs = self.pairs.borrow_mut();
+        if let Some(pair) = pairs.get(&key) {
+            return Some(*pair);
+        }
+        let id = pairs.len() as i32 + 1;
+        if id >= ncurses::COLOR_PAIRS().min(MAX_PAIRS) {
+            return None;
+        }
+        let pair = ColorPair::init(id as i16, key.0, key.1);
+        pairs.insert(key, pair);
+        Some(pair)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names_numbers_and_hex() {
+        assert!(Colour::parse("red") == Some(Colour::Index(ncurses::COLOR_RED)));
+        assert!(Colour::parse("white") == Some(Colour::Index(ncurses::COLOR_WHITE)));
+        assert!(Colour::parse("0") == Some(Colour::Index(0)));
+        assert!(Colour::parse("255") == Some(Colour::Index(255)));
+        assert!(Colour::parse("#ff8000") == Some(Colour::Rgb(255, 128, 0)));
+        assert!(Colour::parse("#A0b0C0") == Some(Colour::Rgb(160, 176, 192)));
+    }
+
+    #[test]
+    fn rejects_bad_colours() {
+        for s in ["", "pink", "-1", "256", "#ff80", "#ff80000", "#gg0000", "ff8000", "#+f8000"] {
+            assert!(Colour::parse(s).is_none(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn palette_covers_basic_cube_and_greys() {
+        assert_eq!(palette_rgb(0), (0, 0, 0));
+        assert_eq!(palette_rgb(15), (255, 255, 255));
+        assert_eq!(palette_rgb(16), (0, 0, 0));
+        assert_eq!(palette_rgb(21), (0, 0, 255));
+        assert_eq!(palette_rgb(196), (255, 0, 0));
+        assert_eq!(palette_rgb(231), (255, 255, 255));
+        assert_eq!(palette_rgb(232), (8, 8, 8));
+        assert_eq!(palette_rgb(255), (238, 238, 238));
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt::Write;
+use std::fs;
+
+use crate::menu::{Setting, SettingValue};
+use crate::rules::{self, GameRules, RuleOverrides};
+use crate::snake_window::KeyLayout;
+use crate::theme::{self, Theme};
+
+pub const CONFIG_PATH: &str = "snake.conf";
+/// Milliseconds per tick on boards that do not set their own speed.
+pub const DEFAULT_SPEED: u64 = 300;
+
+/// The settings the settings screen can change.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum SettingId {
+    Speed,
+    Theme,
+    Keys,
+    HalfBlocks,
+    SquareCells,
+    SmoothSnake,
+    Growth,
+    Foods,
+    SelfCollision,
+    WallCollision,
+    WrapAround,
+    DailyOneAttempt,
+}
+
+/// Global settings read from `key: value` lines in the config file.
+/// Lines starting with # are comments.
+pub struct Config {
+    pub rules: GameRules,
+    /// Allows a single run of each daily challenge.
+    pub daily_one_attempt: bool,
+    /// Starts games drawn with two board rows per line.
+    pub half_blocks: bool,
+    /// Starts games drawn with two columns per board cell.
+    pub square_cells: bool,
+    /// Starts games with the body drawn in box drawing lines.
+    pub smooth_snake: bool,
+    /// The theme to start with, instead of the classic one.
+    pub theme: Option<String>,
+    /// Milliseconds per tick on boards that do not set their own speed.
+    pub speed: u64,
+    pub keys: KeyLayout,
+    /// Problems found reading the file, one per line that has them.
+    pub warnings: Vec<String>,
+}
+
+/// Sets `flag` from a flag value, and tells whether the value was valid.
+fn read_flag(flag: &mut bool, value: &str) -> bool {
+    rules::parse_flag(value).map(|on| *flag = on).is_some()
+}
+
+/// The key of a `key: value` line, None for comments and other lines.
+fn line_key(line: &str) -> Option<&str> {
+    let line = line.trim();
+    line.split_once(':').map(|(key, _)| key.trim()).filter(|_| !line.starts_with('#'))
+}
+
+impl Config {
+    pub fn load(path: &str) -> Config {
+        let mut overrides = RuleOverrides::default();
+        let mut daily_one_attempt = false;
+        let mut half_blocks = false;
+        let mut square_cells = false;
+        let mut smooth_snake = false;
+        let mut theme = None;
+        let mut speed = DEFAULT_SPEED;
+        let mut keys = KeyLayout::Arrows;
+        let mut warnings = Vec::new();
+        let content = fs::read_to_string(path).unwrap_or_default();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    warnings.push(format!("line {}: expected 'key: value', got '{}'", i + 1, line));
+                    continue;
+                }
+            };
+            let valid = match key {
+                "daily_one_attempt" => read_flag(&mut daily_one_attempt, value),
+                "half_blocks" => read_flag(&mut half_blocks, value),
+                "square_cells" => read_flag(&mut square_cells, value),
+                "smooth_snake" => read_flag(&mut smooth_snake, value),
+                "theme" => { theme = Some(value.to_owned()); true },
+                "speed" => value.parse().ok().filter(|speed| *speed > 0).map(|value| speed = value).is_some(),
+                "keys" => KeyLayout::from_name(value).map(|value| keys = value).is_some(),
+                key => match overrides.set(key, value) {
+                    Some(valid) => valid,
+                    None => {
+                        warnings.push(format!("line {}: unknown key '{}'", i + 1, key));
+                        continue;
+                    }
+                },
+            };
+            if !valid {
+                warnings.push(format!("line {}: '{}' is not a valid {}", i + 1, value, key));
+            }
+        }
+        Config {
+            rules: overrides.apply(GameRules::default()),
+            daily_one_attempt,
+            half_blocks,
+            square_cells,
+            smooth_snake,
+            theme,
+            speed,
+            keys,
+            warnings,
+        }
+    }
+
+    /// Writes every setting to `path`. Settings already in the file are
+    /// updated on their own lines and the rest are added at the end.
+    /// Comments, other keys and lines that could not be read are kept.
+    pub fn save(&self, path: &str) {
+        let mut settings = String::new();
+        let _ = writeln!(settings, "speed: {}", self.speed);
+        if let Some(theme) = &self.theme {
+            let _ = writeln!(settings, "theme: {}", theme);
+        }
+        let _ = writeln!(settings, "keys: {}", self.keys.name());
+        let _ = writeln!(settings, "half_blocks: {}", rules::flag_name(self.half_blocks));
+        let _ = writeln!(settings, "square_cells: {}", rules::flag_name(self.square_cells));
+        let _ = writeln!(settings, "smooth_snake: {}", rules::flag_name(self.smooth_snake));
+        let _ = writeln!(settings, "daily_one_attempt: {}", rules::flag_name(self.daily_one_attempt));
+        RuleOverrides::from(self.rules).write(&mut settings);
+
+        let mut pending: Vec<&str> = settings.lines().collect();
+        let content = fs::read_to_string(path).unwrap_or_else(|_| String::from("# Written by the settings screen\n"));
+        let mut out = String::new();
+        for line in content.lines() {
+            // The first line of a setting gets its new value and any later
+            // ones for the same key are dropped.
+            let key = line_key(line);
+            if key.is_none() || settings.lines().all(|setting| line_key(setting) != key) {
+                let _ = writeln!(out, "{}", line);
+            } else if let Some(i) = pending.iter().position(|setting| line_key(setting) == key) {
+                let _ = writeln!(out, "{}", pending.remove(i));
+            }
+        }
+        for setting in pending {
+            let _ = writeln!(out, "{}", setting);
+        }
+        let _ = fs::write(path, out);
+    }
+
+    /// The settings screen's values, in the order they are listed.
+    pub fn settings(&self) -> Vec<Setting<SettingId>> {
+        let themes = Theme::list();
+        let theme = self.theme.as_deref().unwrap_or(theme::DEFAULT_THEME);
+        let toggle = |id, label, on| Setting::new(id, label, SettingValue::Toggle(on));
+        vec![
+            Setting::new(SettingId::Speed, "Speed", SettingValue::Slider {
+                value: self.speed, min: 50, max: 1000, step: 50, unit: "ms",
+            }),
+            Setting::new(SettingId::Theme, "Theme", SettingValue::Choice {
+                selected: themes.iter().position(|name| name == theme).unwrap_or(0),
+                choices: themes,
+            }),
+            Setting::new(SettingId::Keys, "Keys", SettingValue::Choice {
+                choices: KeyLayout::ALL.iter().map(|layout| layout.name().to_owned()).collect(),
+                selected: KeyLayout::ALL.iter().position(|layout| *layout == self.keys).unwrap_or(0),
+            }),
+            toggle(SettingId::HalfBlocks, "Half blocks", self.half_blocks),
+            toggle(SettingId::SquareCells, "Square cells", self.square_cells),
+            toggle(SettingId::SmoothSnake, "Smooth snake", self.smooth_snake),
+            Setting::new(SettingId::Growth, "Growth", SettingValue::Slider {
+                value: self.rules.growth as u64, min: 0, max: 5, step: 1, unit: "",
+            }),
+            Setting::new(SettingId::Foods, "Foods", SettingValue::Slider {
+                value: self.rules.foods as u64, min: 1, max: 9, step: 1, unit: "",
+            }),
+            toggle(SettingId::SelfCollision, "Self collision", self.rules.self_collision),
+            toggle(SettingId::WallCollision, "Wall collision", self.rules.wall_collision),
+            toggle(SettingId::WrapAround, "Wrap around", self.rules.wrap_around),
+            toggle(SettingId::DailyOneAttempt, "One daily try", self.daily_one_attempt),
+        ]
+    }
+
+    /// Takes over the value of a changed setting.
+    pub fn change(&mut self, setting: &Setting<SettingId>) {
+        match setting.id {
+            SettingId::Speed => self.speed = setting.number(),
+            SettingId::Theme => self.theme = setting.choice().map(str::to_owned),
+            SettingId::Keys => self.keys = setting.choice().and_then(KeyLayout::from_name).unwrap_or(self.keys),
+            SettingId::HalfBlocks => self.half_blocks = setting.flag(),
+            SettingId::SquareCells => self.square_cells = setting.flag(),
+            SettingId::SmoothSnake => self.smooth_snake = setting.flag(),
+            SettingId::Growth => self.rules.growth = setting.number() as usize,
+            SettingId::Foods => self.rules.foods = setting.number() as usize,
+            SettingId::SelfCollision => self.rules.self_collision = setting.flag(),
+            SettingId::WallCollision => self.rules.wall_collision = setting.flag(),
+            SettingId::WrapAround => self.rules.wrap_around = setting.flag(),
+            SettingId::DailyOneAttempt => self.daily_one_attempt = setting.flag(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_keeps_comments_and_unknown_keys() {
+        let path = std::env::temp_dir().join(format!("snake-{}.conf", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "# my settings\nspeed: 200\nfavourite: blue\nnot a setting\nspeed: 250\n").unwrap();
+        let mut config = Config::load(path);
+        config.speed = 100;
+        config.save(path);
+        let content = fs::read_to_string(path).unwrap();
+        let speed = Config::load(path).speed;
+        fs::remove_file(path).unwrap();
+
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[..4], ["# my settings", "speed: 100", "favourite: blue", "not a setting"]);
+        assert_eq!(lines.iter().filter(|line| line.starts_with("speed:")).count(), 1);
+        assert!(lines.contains(&"keys: arrows"));
+        assert_eq!(speed, 100);
+    }
+}
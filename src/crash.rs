@@ -0,0 +1,65 @@
+use std::fmt::Write;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::board::{Board, BrickType, Position};
+use crate::board_file;
+use crate::replay::Replay;
+
+const CRASH_DIR: &str = "saves/crashes";
+
+// A crash dump is the replay of the run so far followed by the state the
+// game was in when it panicked:
+//
+//   board Random maze     the replay's lines
+//   seed 20261019
+//   input 5 1
+//   tick 48
+//   facing 1
+//   snake 10 5 11 5 12 5  positions from the head to the tail
+//   food_seed 7316        the seed food spawns were drawn from
+//   food 3 8 17 2         positions of the food on the board
+//   open door             wall groups switched open
+//   keys 2 5              colours of the keys collected
+//   layout                the rest is the board file, without the snake
+//   21 41
+//   ...
+
+fn to_string(board: &Board, replay: &Replay) -> String {
+    let mut out = replay.to_string();
+    let _ = writeln!(out, "tick {}", board.tick);
+    let _ = writeln!(out, "facing {}", board.facing as u8);
+    out += "snake";
+    for pos in &board.snake {
+        let _ = write!(out, " {} {}", pos.0, pos.1);
+    }
+    let _ = write!(out, "\nfood_seed {}\nfood", board.food_seed);
+    for x in 0..board.x_size() {
+        for y in 0..board.y_size() {
+            if board[Position(x, y)] == BrickType::Food {
+                let _ = write!(out, " {} {}", x, y);
+            }
+        }
+    }
+    out += "\nopen";
+    for group in board.wall_groups.iter().filter(|group| !group.solid) {
+        let _ = write!(out, " {}", group.name);
+    }
+    let mut keys: Vec<_> = board.keys.iter().collect();
+    keys.sort();
+    out += "\nkeys";
+    for colour in keys {
+        let _ = write!(out, " {}", colour);
+    }
+    out += "\nlayout\n";
+    out + &board_file::layout_to_string(board)
+}
+
+/// Writes a dump of the run into the crash directory and returns its path.
+pub fn save(board: &Board, replay: &Replay) -> Option<String> {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let path = format!("{}/crash-{}.dump", CRASH_DIR, time);
+    fs::create_dir_all(CRASH_DIR).ok()?;
+    fs::write(&path, to_string(board, replay)).ok()?;
+    Some(path)
+}
@@ -0,0 +1,108 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::generator::Style;
+use crate::replay::REPLAY_DIR;
+
+const HISTORY_PATH: &str = "saves/daily.history";
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Today's date in UTC, so every player shares the same challenge.
+    pub fn today() -> Date {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+        Date::from_days((secs / 86400) as i64)
+    }
+
+    /// Converts days since 1970-01-01 to a calendar date.
+    fn from_days(days: i64) -> Date {
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days.rem_euclid(146097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+        Date {
+            year: year_of_era + era * 400 + (month <= 2) as i64,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        (self.year * 10000) as u64 + (self.month * 100 + self.day) as u64
+    }
+
+    pub fn style(&self) -> Style {
+        Style::ALL[(self.seed() % Style::ALL.len() as u64) as usize]
+    }
+
+    pub fn replay_path(&self, attempt: usize) -> String {
+        format!("{}/daily-{}-{}.replay", REPLAY_DIR, self, attempt)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// One daily run, stored as a `date score` line, or `date -` for a run
+/// that was started but never finished.
+pub struct DailyResult {
+    pub date: String,
+    pub score: Option<usize>,
+}
+
+const UNFINISHED: &str = "-";
+
+pub fn load_history() -> Vec<DailyResult> {
+    let content = fs::read_to_string(HISTORY_PATH).unwrap_or_default();
+    content.lines()
+        .filter_map(|line| {
+            let mut iter = line.split_whitespace();
+            let date = iter.next()?.to_owned();
+            let score = match iter.next()? {
+                UNFINISHED => None,
+                score => Some(score.parse().ok()?),
+            };
+            Some(DailyResult { date, score })
+        })
+        .collect()
+}
+
+/// Counts a run as played as soon as it starts, so that quitting the game
+/// mid-run still uses up the day's attempt.
+pub fn record_start(date: &str) {
+    if let Some(dir) = Path::new(HISTORY_PATH).parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(HISTORY_PATH) {
+        let _ = writeln!(file, "{} {}", date, UNFINISHED);
+    }
+}
+
+/// Fills in the score of the last unfinished run of the result's date.
+pub fn record_result(result: &DailyResult) {
+    let content = fs::read_to_string(HISTORY_PATH).unwrap_or_default();
+    let mut lines: Vec<String> = content.lines().map(str::to_owned).collect();
+    let score = result.score.map_or(UNFINISHED.to_owned(), |score| score.to_string());
+    let line = format!("{} {}", result.date, score);
+    match lines.iter().rposition(|line| *line == format!("{} {}", result.date, UNFINISHED)) {
+        Some(i) => lines[i] = line,
+        None => lines.push(line),
+    }
+    let _ = fs::write(HISTORY_PATH, lines.join("\n") + "\n");
+}
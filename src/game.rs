@@ -1,93 +1,636 @@
-use std::{time, thread};
+use std::{fs, time, thread};
+use std::panic::{self, AssertUnwindSafe};
 
+use crate::board::Board;
 use crate::board_file;
+use crate::board_metadata::BoardMetadata;
+use crate::campaign::Campaign;
+use crate::config::{Config, SettingId, CONFIG_PATH};
+use crate::daily::{self, Date, DailyResult};
 use crate::event_emitter::EventEmitter;
+use crate::generator::{self, Style};
+use crate::layout::{self, Size, Split};
+use crate::menu::{Entry, Setting};
+use crate::replay::Replay;
+use crate::rules::GameRules;
 use crate::selection_window::{SelectionWindow, SelectionWindowEvent, SelectionWindowEventId};
-use crate::basic_window::{Window, NcursesContext, Dimensions};
-use crate::snake_window::SnakeWindow;
+use crate::basic_window::{self, Window, NcursesContext, Dimensions, BasicWindow};
+use crate::snake_window::{SnakeWindow, GameState};
+use crate::theme::Theme;
+use crate::widgets::{Dialog, Frame, Label, ProgressBar};
+
+/// Lines under a menu for the highlighted option's details.
+const DETAILS_HEIGHT: i32 = 8;
+/// Room for the values on the settings screen.
+const SETTING_VALUE_WIDTH: i32 = 24;
+/// Digits of a seed typed in, few enough to always fit a u64.
+const SEED_DIGITS: usize = 19;
+/// The width of the stats screen.
+const STATS_WIDTH: i32 = 44;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum MainId {
+    Play,
+    Boards,
+    Settings,
+    Stats,
+    Replays,
+    Quit,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum PlayId {
+    Default,
+    Random,
+    Daily,
+    Campaign,
+}
 
 pub struct Game<'a> {
     context: &'a NcursesContext,
+    config: Config,
+}
+
+struct BoardEntry {
+    path: String,
+    stem: String,
+    metadata: BoardMetadata,
+}
+
+impl BoardEntry {
+    fn name(&self) -> String {
+        self.metadata.name.clone().unwrap_or_else(|| self.stem.clone())
+    }
+
+    fn details(&self) -> Vec<String> {
+        let metadata = &self.metadata;
+        let mut details: Vec<String> = metadata.description.iter().cloned().collect();
+        if let Some(author) = &metadata.author {
+            details.push(format!("by {}", author));
+        }
+        if let Some(difficulty) = metadata.difficulty {
+            details.push(format!("difficulty {}", difficulty));
+        }
+        if !metadata.tags.is_empty() {
+            details.push(metadata.tags.join(", "));
+        }
+        details.extend(metadata.warnings.iter().map(|warning| format!("warning: {}", warning)));
+        details
+    }
+}
+
+/// Finds the file of a board named by its file stem, in either format.
+fn board_path(file: &str) -> Option<String> {
+    ["board", "grid"].iter()
+        .map(|ext| format!("boards/{}.{}", file, ext))
+        .find(|path| fs::metadata(path).is_ok())
+}
+
+/// Lists the boards in the boards directory, sorted by file name.
+fn list_boards() -> Vec<BoardEntry> {
+    let mut paths: Vec<_> = match fs::read_dir("boards") {
+        Ok(dir) => dir.filter_map(|entry| Some(entry.ok()?.path())).collect(),
+        Err(_) => Vec::new(),
+    };
+    paths.sort();
+    paths.into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "board" || ext == "grid"))
+        .filter_map(|path| {
+            let path_str = path.to_str()?.to_owned();
+            Some(BoardEntry {
+                stem: path.file_stem()?.to_str()?.to_owned(),
+                metadata: board_file::read_metadata(&path_str)?,
+                path: path_str,
+            })
+        })
+        .collect()
 }
 
 impl Game<'_> {
 
     pub fn new<'b>(context: &'b NcursesContext) -> Game<'b> {
+        let mut config = Config::load(CONFIG_PATH);
+        if let Some(name) = &config.theme {
+            match Theme::load(name) {
+                Ok(theme) => { context.get_visuals().apply_theme(&theme); },
+                Err(err) => config.warnings.push(format!("theme '{}': {}", name, err)),
+            }
+        }
         Game {
             context,
+            config,
         }
     }
 
-    fn run_game(&self, mut win: SnakeWindow) {
+    fn run_game(&self, win: &mut SnakeWindow) -> GameState {
+        if self.config.half_blocks {
+            win.set_half_blocks(true);
+        }
+        if self.config.square_cells {
+            win.set_square_cells(true);
+        }
+        if self.config.smooth_snake {
+            win.set_smooth_snake(true);
+        }
+        win.set_keys(self.config.keys);
+        basic_window::clear_screen();
         win.draw();
         self.context.clear_key_queue();
         self.context.get_key();
-        let wait_time = time::Duration::from_millis(300);
-        loop {
+        let wait_time = time::Duration::from_millis(win.get_board().metadata.speed.unwrap_or(self.config.speed));
+        let run = panic::catch_unwind(AssertUnwindSafe(|| loop {
             thread::sleep(wait_time);
             let mut key: Option<i32>;
             while { key = self.context.get_last_pressed_key(); key.is_some() } {
                 win.handle_keypress(key.unwrap());
                 win.refresh();
             }
-            if win.step() {
-                win.refresh();
-                break;
-            }
+            let state = win.step();
             win.refresh();
-        }
+            if state != GameState::Running {
+                break state;
+            }
+        }));
+        let state = match run {
+            Ok(state) => state,
+            Err(err) => {
+                if let Some(path) = win.save_crash_dump() {
+                    eprintln!("crash dump written to {}", path);
+                }
+                panic::resume_unwind(err);
+            },
+        };
         self.context.clear_key_queue();
         self.context.get_key();
+        state
     }
 
     fn default_board(&self) -> SnakeWindow {
-        SnakeWindow::new_default(self.context, 15, 30)
+        let mut board = Board::new_default(15, 30);
+        board.rules = self.config.rules;
+        SnakeWindow::new(self.context, board)
     }
 
     fn from_file(&self, file: &str) -> Option<SnakeWindow> {
-        let path = "boards/".to_owned() + file + ".board";
-        Some(SnakeWindow::new(self.context, board_file::from_file(path.as_str())?))
+        self.load_board(&board_path(file)?)
+    }
+
+    fn load_board(&self, path: &str) -> Option<SnakeWindow<'_>> {
+        let mut board = board_file::from_file(path)?;
+        board.rules = board.metadata.rules.apply(self.config.rules);
+        Some(SnakeWindow::new(self.context, board))
+    }
+
+    fn random_board(&self, style: Style, seed: u64) -> SnakeWindow<'_> {
+        let mut board = generator::generate(style, seed);
+        board.rules = self.config.rules;
+        let fallback = board.seed.is_none();
+        let mut win = SnakeWindow::new(self.context, board);
+        if fallback {
+            win.set_info(vec!["not generated".to_owned()]);
+        }
+        win
+    }
+
+    /// Plays one generated board, as asked for on the command line.
+    pub fn run_random(&self, style: Style, seed: u64) {
+        self.run_game(&mut self.random_board(style, seed));
+    }
+
+    fn run_random_menu(&self) {
+        let entries: Vec<Entry<Style>> = Style::ALL.iter()
+            .map(|style| Entry::new(*style, style.name()).with_details(vec![style.describe().to_owned()]))
+            .collect();
+        let mut selected = None;
+        while let Some(style) = self.choose(&entries, selected, true) {
+            selected = Some(style);
+            if let Some(seed) = self.ask_seed() {
+                self.run_random(style, seed);
+            }
+        }
+    }
+
+    /// Asks for the seed of a random board. An empty one picks a random
+    /// seed, None means the player went back.
+    fn ask_seed(&self) -> Option<u64> {
+        let mut dialog = Dialog::prompt(
+            "Seed",
+            vec!["Type a seed to replay a board,".to_owned(), "or leave it empty for a new one".to_owned()],
+            vec!["Play".to_owned(), "Back".to_owned()],
+            SEED_DIGITS,
+            |c| c.is_ascii_digit(),
+        );
+        match dialog.run(self.context) {
+            Some(0) => Some(dialog.input().parse().unwrap_or_else(|_| rand::random::<u32>() as u64)),
+            _ => None,
+        }
+    }
+
+    /// Shows `message` in a dialog until it is closed.
+    fn tell(&self, title: &str, message: Vec<String>) {
+        Dialog::new(title, message, vec!["OK".to_owned()]).run(self.context);
+    }
+
+    /// Today's run count and best score, for the menu.
+    fn daily_details(&self, date: Date) -> (Vec<String>, bool) {
+        let today = date.to_string();
+        let scores: Vec<Option<usize>> = daily::load_history().into_iter()
+            .filter(|result| result.date == today)
+            .map(|result| result.score)
+            .collect();
+        let mut details = vec![format!("Challenge of {}: {}", today, date.style().describe())];
+        match scores.iter().flatten().max() {
+            _ if scores.is_empty() => details.push("not played yet today".to_owned()),
+            Some(best) => details.push(format!("played {} times today, best score {}", scores.len(), best)),
+            None => details.push(format!("played {} times today, never finished", scores.len())),
+        }
+        let locked = self.config.daily_one_attempt && !scores.is_empty();
+        if self.config.daily_one_attempt {
+            details.push("one attempt per day".to_owned());
+        }
+        (details, locked)
+    }
+
+    /// Plays today's challenge with the default rules so that every run
+    /// is the same. The attempt is recorded before the run starts, its
+    /// score and a replay once it ends.
+    fn run_daily(&self) {
+        let date = Date::today();
+        let today = date.to_string();
+        let attempt = daily::load_history().iter().filter(|result| result.date == today).count() + 1;
+        let mut board = generator::generate(date.style(), date.seed());
+        board.rules = GameRules::default();
+        let mut win = SnakeWindow::new(self.context, board);
+        win.set_info(vec!["daily".to_owned(), today.clone()]);
+        daily::record_start(&today);
+        self.run_game(&mut win);
+
+        let replay = win.replay();
+        replay.date = Some(today.clone());
+        replay.save(&date.replay_path(attempt));
+        daily::record_result(&DailyResult { date: today, score: Some(replay.score) });
+    }
+
+    fn run_play_menu(&self) {
+        let mut selected = None;
+        loop {
+            let (daily_details, daily_locked) = self.daily_details(Date::today());
+            let entries = [
+                Entry::new(PlayId::Default, "Default").with_details(vec!["An empty board surrounded by walls".to_owned()]),
+                Entry::new(PlayId::Random, "Random").with_details(vec!["A new board generated from a random seed".to_owned()]),
+                Entry::new(PlayId::Daily, "Daily").with_details(daily_details).disable(daily_locked),
+                Entry::new(PlayId::Campaign, "Campaign").with_details(vec!["Levels with targets and lives".to_owned()]),
+            ];
+            selected = self.choose(&entries, selected, true);
+            match selected {
+                Some(PlayId::Default) => { self.run_game(&mut self.default_board()); },
+                Some(PlayId::Random) => self.run_random_menu(),
+                Some(PlayId::Daily) => self.run_daily(),
+                Some(PlayId::Campaign) => self.run_campaign("main"),
+                None => return,
+            }
+        }
+    }
+
+    fn run_boards_menu(&self) {
+        let boards = list_boards();
+        let entries: Vec<Entry<usize>> = boards.iter().enumerate()
+            .map(|(i, board)| Entry::new(i, board.name()).with_details(board.details()))
+            .collect();
+        let mut selected = None;
+        while let Some(i) = self.choose(&entries, selected, true) {
+            selected = Some(i);
+            match self.load_board(&boards[i].path) {
+                Some(mut win) => { self.run_game(&mut win); },
+                None => self.tell("Error", vec![format!("Could not load {}", boards[i].path)]),
+            }
+        }
+    }
+
+    /// Help for a setting, or the description of the theme it picks.
+    fn setting_details(&self, setting: &Setting<SettingId>) -> Vec<String> {
+        let help = match setting.id {
+            SettingId::Speed => "Time between steps on boards without their own speed",
+            SettingId::Theme => return match setting.choice().map(Theme::load) {
+                Some(Ok(theme)) => vec![theme.description],
+                Some(Err(err)) => vec![format!("error: {}", err)],
+                None => Vec::new(),
+            },
+            SettingId::Keys => "Letters that steer besides the arrows: hjkl for vim, 8462 for numpad",
+            SettingId::HalfBlocks => "Draw two board rows per line, z toggles it in game",
+            SettingId::SquareCells => "Draw cells two columns wide, s toggles it in game",
+            SettingId::SmoothSnake => "Draw the body with lines, b toggles it in game",
+            SettingId::Growth => "Segments the snake grows by for every food",
+            SettingId::Foods => "Foods on the board at the same time",
+            SettingId::SelfCollision => "Running into the snake's own body ends the game",
+            SettingId::WallCollision => "Running into a wall ends the game",
+            SettingId::WrapAround => "Leaving the board at an edge comes back at the opposite one",
+            SettingId::DailyOneAttempt => "Allow a single run of each daily challenge",
+        };
+        vec![help.to_owned(), "left and right change the value".to_owned()]
+    }
+
+    /// Changes a setting and applies it right away where that shows.
+    fn change_setting(&mut self, setting: &mut Setting<SettingId>, forward: bool) {
+        setting.change(forward);
+        self.config.change(setting);
+        if setting.id == SettingId::Theme {
+            if let Some(Ok(theme)) = setting.choice().map(Theme::load) {
+                self.context.get_visuals().apply_theme(&theme);
+            }
+        }
+    }
+
+    /// Lists the settings with their values, which the left and right
+    /// arrows change in place. Leaving the screen saves them.
+    fn run_settings(&mut self) {
+        let mut settings = self.config.settings();
+        if !self.context.get_visuals().common_visuals.has_colour() {
+            settings.retain(|setting| setting.id != SettingId::Theme);
+        }
+        let column = settings.iter().map(|setting| setting.label.len()).max().unwrap_or(0) + 2;
+        let mut options: Vec<String> = settings.iter().map(|setting| setting.text(column)).collect();
+        options.push("Back".to_owned());
+        let mut menu = self.new_menu(options, Vec::new(), column as i32 + SETTING_VALUE_WIDTH, 0);
+        let details_win = BasicWindow::new(Dimensions::new(menu.height(), 0, DETAILS_HEIGHT, ncurses::COLS()));
+        basic_window::clear_screen();
+        menu.draw();
+
+        loop {
+            let highlighted = menu.get_selection().filter(|i| *i < settings.len());
+            let details = highlighted.map_or(Vec::new(), |i| self.setting_details(&settings[i]));
+            self.draw_details(&details_win, &details);
+
+            let key = self.context.get_key();
+            let change = match key {
+                ncurses::KEY_LEFT => Some(false),
+                ncurses::KEY_RIGHT => Some(true),
+                ncurses::KEY_RESIZE => {
+                    basic_window::clear_screen();
+                    menu.draw();
+                    None
+                },
+                key => {
+                    menu.handle_keypress(key);
+                    let mut events = Vec::new();
+                    menu.get_pool().handle_events(&mut |event| events.push(event));
+                    match events.into_iter().find(|event| !matches!(event, SelectionWindowEvent::HighlightChanged(_))) {
+                        Some(SelectionWindowEvent::Select(i)) if i < settings.len() => Some(true),
+                        Some(_) => break,
+                        None => None,
+                    }
+                },
+            };
+            if let (Some(forward), Some(i)) = (change, highlighted) {
+                self.change_setting(&mut settings[i], forward);
+                menu.set_option(i, settings[i].text(column));
+            }
+            menu.refresh();
+        }
+        self.config.save(CONFIG_PATH);
+    }
+
+    /// Daily challenge results and campaign progress, until a key is
+    /// pressed.
+    fn show_stats(&self) {
+        let history = daily::load_history();
+        let mut daily = vec!["Daily".to_owned(), format!("  {} runs", history.len())];
+        let best = history.iter().filter_map(|result| Some((result.score?, &result.date))).max_by_key(|(score, _)| *score);
+        if let Some((score, date)) = best {
+            daily.push(format!("  best score {} on {}", score, date));
+        }
+        let mut days: Vec<&str> = history.iter().map(|result| result.date.as_str()).collect();
+        days.dedup();
+        daily.push(format!("  played on {} days", days.len()));
+
+        let mut campaign = vec!["Campaign".to_owned()];
+        let unlocked = Campaign::from_file("main").map(|main| {
+            let progress = main.load_progress();
+            campaign.push(format!("  score {}, {} lives left", progress.score, progress.lives));
+            campaign.push("  levels unlocked".to_owned());
+            (progress.unlocked + 1, main.levels.len())
+        });
+        if unlocked.is_none() {
+            campaign.push("  no campaign found".to_owned());
+        }
+
+        loop {
+            let height = (daily.len() + campaign.len()) as i32 + unlocked.is_some() as i32 + 5;
+            let frame = Frame::new(Dimensions::centred(height, STATS_WIDTH), "Stats");
+            let content = layout::split(&frame.inner(), Split::Horizontal, &[Size::Fixed(1), Size::Flex(1), Size::Fixed(1)])[1];
+            let areas = layout::split(&content, Split::Vertical, &[
+                Size::Fixed(daily.len() as i32),
+                Size::Fixed(1),
+                Size::Fixed(campaign.len() as i32),
+                Size::Fixed(unlocked.is_some() as i32),
+                Size::Flex(1),
+                Size::Fixed(1),
+            ]);
+            let bar_area = layout::split(&areas[3], Split::Horizontal, &[Size::Fixed(2), Size::Flex(1)])[1];
+            basic_window::clear_screen();
+            frame.draw();
+            Label::new(areas[0], daily.clone()).draw();
+            Label::new(areas[2], campaign.clone()).draw();
+            if let Some((unlocked, levels)) = unlocked {
+                ProgressBar::new(bar_area, unlocked, levels).draw();
+            }
+            Label::new(areas[5], vec!["any key to go back".to_owned()]).centred().draw();
+            if self.context.get_key() != ncurses::KEY_RESIZE {
+                return;
+            }
+        }
+    }
+
+    /// The saved replays with what they recorded.
+    fn show_replays(&self) {
+        let entries: Vec<Entry<()>> = Replay::list().into_iter()
+            .filter_map(|path| {
+                let replay = Replay::load(&path)?;
+                let name = path.rsplit('/').next()?.trim_end_matches(".replay").to_owned();
+                let mut details = vec![format!("board {}", replay.board)];
+                details.extend(replay.date.map(|date| format!("played on {}", date)));
+                details.extend(replay.seed.map(|seed| format!("seed {}", seed)));
+                details.push(format!("score {}, {} turns", replay.score, replay.inputs.len()));
+                Some(Entry::new((), name).with_details(details))
+            })
+            .collect();
+        while self.choose(&entries, None, true).is_some() {}
+    }
+
+    /// Runs a menu of `entries`, with a Back entry after them when `back`
+    /// is set, and gives the id of the entry picked. Gives None for Back
+    /// and when the menu is cancelled.
+    fn choose<Id: Copy + PartialEq>(&self, entries: &[Entry<Id>], selected: Option<Id>, back: bool) -> Option<Id> {
+        let mut options: Vec<String> = entries.iter().map(|entry| entry.label.clone()).collect();
+        if back {
+            options.push("Back".to_owned());
+        }
+        let mut descriptions: Vec<String> = entries.iter().map(|entry| entry.description.clone()).collect();
+        if descriptions.iter().all(String::is_empty) {
+            descriptions.clear();
+        }
+        let details: Vec<Vec<String>> = entries.iter().map(|entry| entry.details.clone()).collect();
+        let width = options.iter().chain(&descriptions).map(|opt| opt.len() as i32 + 4).max().unwrap_or(0).max(17);
+        let selected = selected.and_then(|id| entries.iter().position(|entry| entry.id == id)).unwrap_or(0);
+
+        let mut menu = self.new_menu(options, descriptions, width, selected);
+        for (i, entry) in entries.iter().enumerate() {
+            menu.set_disabled(i, entry.disabled);
+        }
+        self.run_menu(menu, &details).and_then(|i| entries.get(i)).map(|entry| entry.id)
+    }
+
+    /// A menu in the top left corner, as tall as the screen leaves room
+    /// for above the details. Longer lists scroll.
+    fn new_menu(&self, options: Vec<String>, descriptions: Vec<String>, width: i32, selected: usize) -> SelectionWindow {
+        let reserved = 2 + !descriptions.is_empty() as i32;
+        let rows = (options.len() as i32).min(basic_window::screen_size().0 - DETAILS_HEIGHT - reserved).max(1);
+        let dim = Dimensions::new(0, 0, rows + reserved, width);
+        let mut menu = SelectionWindow::new_selected(self.context, dim, options, Some(selected));
+        menu.set_descriptions(descriptions);
+        menu.get_pool().listen(&[
+            SelectionWindowEventId::Select,
+            SelectionWindowEventId::Cancel,
+            SelectionWindowEventId::HighlightChanged,
+        ]);
+        menu
     }
 
-    fn run_menu(&self, mut win: SelectionWindow) -> Option<SnakeWindow> {
+    fn draw_details(&self, win: &BasicWindow, details: &[String]) {
+        win.erase();
+        for (i, line) in details.iter().enumerate() {
+            win.move_print(i as i32, 2, line);
+        }
+        win.refresh();
+    }
+
+    /// Runs the menu until an option is picked and returns its index, or
+    /// None when the menu is cancelled. `details` holds the lines shown
+    /// under the menu for each option.
+    fn run_menu(&self, mut win: SelectionWindow, details: &[Vec<String>]) -> Option<usize> {
+        let details_win = BasicWindow::new(Dimensions::new(win.height(), 0, DETAILS_HEIGHT, ncurses::COLS()));
+        let show_details = |highlighted: Option<usize>| {
+            let lines = highlighted.and_then(|i| details.get(i));
+            self.draw_details(&details_win, lines.map_or(&[], |lines| lines.as_slice()));
+        };
+        basic_window::clear_screen();
         win.draw();
-        let mut exit = false;
-        let mut snake_win: Option<SnakeWindow> = None;
-
-        while !exit {
-
-            let mut event_callback = |event: SelectionWindowEvent| {
-                match event {
-                    SelectionWindowEvent::Select(opt) => { 
-                        match opt.as_str() {
-                            "Exit" => { exit = true; },
-                            "Default" => { snake_win = Some(self.default_board()); exit = true; },
-                            _ => { snake_win = self.from_file(opt.as_str()); exit = true; }
+        show_details(win.get_selection());
+
+        loop {
+            let key = self.context.get_key();
+            if key == ncurses::KEY_RESIZE {
+                basic_window::clear_screen();
+                win.draw();
+                show_details(win.get_selection());
+                continue;
+            }
+            win.handle_keypress(key);
+            win.refresh();
+
+            let mut result = None;
+            win.get_pool().handle_events(&mut |event| match event {
+                SelectionWindowEvent::Select(i) => result = Some(Some(i)),
+                SelectionWindowEvent::Cancel => result = Some(None),
+                SelectionWindowEvent::HighlightChanged(i) => show_details(Some(i)),
+            });
+            if let Some(result) = result {
+                return result;
+            }
+        }
+    }
+
+    fn run_campaign(&self, name: &str) {
+        let campaign = match Campaign::from_file(name) {
+            Some(campaign) => campaign,
+            None => return self.tell("Error", vec![format!("Could not load campaign {}", name)]),
+        };
+        let mut progress = campaign.load_progress();
+        loop {
+            let entries: Vec<Entry<usize>> = campaign.levels.iter().enumerate()
+                .map(|(i, level)| {
+                    let metadata = board_path(&level.board).and_then(|path| board_file::read_metadata(&path));
+                    let name = metadata.and_then(|metadata| metadata.name).unwrap_or_else(|| level.board.clone());
+                    Entry::new(i, format!("{}. {}", i + 1, name))
+                        .with_details(vec![level.target.describe()])
+                        .disable(i > progress.unlocked)
+                })
+                .collect();
+            let mut level = match self.choose(&entries, Some(progress.unlocked), true) {
+                Some(level) => level,
+                None => return,
+            };
+
+            while level < campaign.levels.len() {
+                let target = campaign.levels[level].target;
+                let mut win = match self.from_file(&campaign.levels[level].board) {
+                    Some(win) => win,
+                    None => {
+                        self.tell("Error", vec![format!("Could not load level {}, {}", level + 1, campaign.levels[level].board)]);
+                        break;
+                    },
+                };
+                win.set_target(target);
+                win.set_info(vec![
+                    format!("level {}/{}", level + 1, campaign.levels.len()),
+                    format!("lives {}", progress.lives),
+                    format!("total {}", progress.score),
+                    target.describe(),
+                ]);
+                match self.run_game(&mut win) {
+                    GameState::Won => {
+                        progress.score += win.score();
+                        level += 1;
+                        progress.unlocked = progress.unlocked.max(level.min(campaign.levels.len() - 1));
+                        if level == campaign.levels.len() {
+                            self.tell("Campaign complete", vec![
+                                format!("All {} levels finished", campaign.levels.len()),
+                                format!("Total score {}", progress.score),
+                            ]);
                         }
                     },
+                    _ => {
+                        progress.lives = progress.lives.saturating_sub(1);
+                        if progress.lives == 0 {
+                            self.tell("Game over", vec![
+                                "No lives left".to_owned(),
+                                "The score starts over, unlocked levels stay".to_owned(),
+                            ]);
+                            progress = campaign.fresh_progress(progress.unlocked);
+                            campaign.save_progress(&progress);
+                            break;
+                        }
+                    }
                 }
-            };
-
-            win.handle_keypress(self.context.get_key());
-            win.refresh();
-            win.get_pool().handle_events(&mut event_callback);
+                campaign.save_progress(&progress);
+            }
         }
-        snake_win
     }
 
     pub fn run(&mut self) {
+        if !self.config.warnings.is_empty() {
+            self.tell(CONFIG_PATH, self.config.warnings.clone());
+        }
+        let mut selected = None;
         loop {
-            let options: Vec<String> = ["Default", "u_pattern", "simple_portal", "Exit"].into_iter().map(String::from).collect();
-            let mut menu = SelectionWindow::new_selected(
-                self.context, Dimensions::new(0, 0, 6, 17), options, Some(0)
-            );
-            menu.get_pool().listen(&[SelectionWindowEventId::SelectId]);
-            let board = self.run_menu(menu);
-            if board.is_none() {
-                break;
+            let entries = [
+                Entry::new(MainId::Play, "Play").describe("Default, random, daily and campaign games"),
+                Entry::new(MainId::Boards, "Boards").describe("Boards from the boards directory"),
+                Entry::new(MainId::Settings, "Settings").describe("Speed, looks, keys and rules"),
+                Entry::new(MainId::Stats, "Stats").describe("Daily results and campaign progress"),
+                Entry::new(MainId::Replays, "Replays").describe("Saved runs of daily challenges"),
+                Entry::new(MainId::Quit, "Quit").describe("Leave the game"),
+            ];
+            selected = self.choose(&entries, selected, false);
+            match selected {
+                Some(MainId::Play) => self.run_play_menu(),
+                Some(MainId::Boards) => self.run_boards_menu(),
+                Some(MainId::Settings) => self.run_settings(),
+                Some(MainId::Stats) => self.show_stats(),
+                Some(MainId::Replays) => self.show_replays(),
+                Some(MainId::Quit) => break,
+                None => {},
             }
-
-            self.run_game(board.unwrap());
         }
     }
 
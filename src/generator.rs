@@ -0,0 +1,338 @@
+use std::collections::{HashSet, VecDeque};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::board::*;
+use crate::board_file::BoardBuilder;
+
+pub const X_SIZE: usize = 21;
+pub const Y_SIZE: usize = 41;
+const SNAKE_LEN: usize = 4;
+const ATTEMPTS: usize = 20;
+/// Cells around the centre that scattered obstacles leave free.
+const CLEARING: (usize, usize) = (4, 1);
+/// Chance of knocking out a maze wall to make a loop.
+const BRAID: f64 = 0.1;
+/// Longest chain of portals followed by the connectivity check.
+const PORTAL_CHAIN: usize = 8;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Style {
+    Obstacles,
+    Maze,
+    Rooms,
+}
+
+impl Style {
+    pub const ALL: [Style; 3] = [Style::Obstacles, Style::Maze, Style::Rooms];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Style::Obstacles => "obstacles",
+            Style::Maze => "maze",
+            Style::Rooms => "rooms",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Style> {
+        Style::ALL.into_iter().find(|style| style.name() == name)
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Style::Obstacles => "Short walls scattered over an open field",
+            Style::Maze => "Winding corridors with a few loops",
+            Style::Rooms => "Closed rooms connected by portals",
+        }
+    }
+}
+
+/// Generates a board of the given style. The same style and seed always
+/// give the same board, with food spawning in the same places. When every
+/// attempt fails this falls back to the default board, which has no seed
+/// since the seed does not describe it, though its food still follows it.
+pub fn generate(style: Style, seed: u64) -> Board {
+    let mut rng = StdRng::seed_from_u64(seed);
+    match (0..ATTEMPTS).find_map(|_| try_generate(style, seed, &mut rng)) {
+        Some(mut board) => {
+            board.seed = Some(seed);
+            board.metadata.name = Some(format!("Random {}", style.name()));
+            board.metadata.description = Some(format!("{}, seed {}", style.describe(), seed));
+            board
+        },
+        None => {
+            let mut board = Board::new_default(X_SIZE, Y_SIZE);
+            board.seed_food(seed);
+            board.metadata.name = Some("Default".to_owned());
+            board.metadata.description = Some(format!("No {} board could be generated from seed {}", style.name(), seed));
+            board
+        },
+    }
+}
+
+fn try_generate(style: Style, seed: u64, rng: &mut StdRng) -> Option<Board> {
+    let mut builder = BoardBuilder::new(X_SIZE, Y_SIZE);
+    let board = builder.board();
+    board.seed_food(seed);
+    match style {
+        Style::Obstacles => obstacles(board, rng),
+        Style::Maze => maze(board, rng),
+        Style::Rooms => rooms(board, rng)?,
+    }
+    place_snake(board)?;
+
+    let reachable = reachable_cells(board, *board.snake.front()?);
+    if reachable.len() < X_SIZE * Y_SIZE / 4 {
+        return None;
+    }
+    for x in 0..X_SIZE {
+        for y in 0..Y_SIZE {
+            let pos = Position(x, y);
+            if board.get_terrain(pos).is_floor() && !reachable.contains(&pos) {
+                board.set_terrain(pos, BrickType::Wall);
+            }
+        }
+    }
+    Some(builder.build())
+}
+
+fn fill(board: &mut Board, brick: BrickType) {
+    for row in board.terrain.iter_mut() {
+        row.fill(brick.clone());
+    }
+}
+
+/// Draws the outline of the rectangle between two corners.
+fn outline(board: &mut Board, Position(x0, y0): Position, Position(x1, y1): Position) {
+    for x in x0..=x1 {
+        board.set_terrain(Position(x, y0), BrickType::Wall);
+        board.set_terrain(Position(x, y1), BrickType::Wall);
+    }
+    for y in y0..=y1 {
+        board.set_terrain(Position(x0, y), BrickType::Wall);
+        board.set_terrain(Position(x1, y), BrickType::Wall);
+    }
+}
+
+fn in_clearing(Position(x, y): Position) -> bool {
+    x.abs_diff(X_SIZE / 2) <= CLEARING.0 && y.abs_diff(Y_SIZE / 2) <= CLEARING.1
+}
+
+fn obstacles(board: &mut Board, rng: &mut StdRng) {
+    outline(board, Position(0, 0), Position(X_SIZE - 1, Y_SIZE - 1));
+    for _ in 0..X_SIZE * Y_SIZE / 40 {
+        let mut pos = Position(rng.gen_range(1..X_SIZE - 1), rng.gen_range(1..Y_SIZE - 1));
+        let dir = *[Direction::Right, Direction::Down].choose(rng).unwrap();
+        for _ in 0..rng.gen_range(1..=4) {
+            if pos.0 == 0 || pos.0 >= X_SIZE - 1 || pos.1 == 0 || pos.1 >= Y_SIZE - 1 {
+                break;
+            }
+            if !in_clearing(pos) {
+                board.set_terrain(pos, BrickType::Wall);
+            }
+            pos = pos.move_dir(dir);
+        }
+    }
+}
+
+/// Carves a maze with a randomised depth first search over the cells at
+/// odd coordinates. A straight corridor through the centre is carved
+/// first so the snake has somewhere to start.
+fn maze(board: &mut Board, rng: &mut StdRng) {
+    fill(board, BrickType::Wall);
+    let mut visited = HashSet::new();
+    let mut stack = Vec::new();
+    let y = (Y_SIZE / 2) | 1;
+    let start = ((X_SIZE / 2) | 1) - 4;
+    for x in (start..=start + 8).step_by(2) {
+        board.set_terrain(Position(x, y), BrickType::None);
+        if x > start {
+            board.set_terrain(Position(x - 1, y), BrickType::None);
+        }
+        visited.insert(Position(x, y));
+        stack.push(Position(x, y));
+    }
+
+    while let Some(&cell) = stack.last() {
+        let options: Vec<(Position, Position)> = Direction::ALL.iter()
+            .filter_map(|dir| {
+                let between = board.neighbour(cell, *dir)?;
+                let next = board.neighbour(between, *dir)?;
+                let Position(x, y) = next;
+                let inside = x > 0 && x < X_SIZE - 1 && y > 0 && y < Y_SIZE - 1;
+                (inside && !visited.contains(&next)).then_some((between, next))
+            })
+            .collect();
+        match options.choose(rng) {
+            Some(&(between, next)) => {
+                board.set_terrain(between, BrickType::None);
+                board.set_terrain(next, BrickType::None);
+                visited.insert(next);
+                stack.push(next);
+            },
+            None => { stack.pop(); },
+        }
+    }
+
+    for x in 1..X_SIZE - 1 {
+        for y in 1..Y_SIZE - 1 {
+            let pos = Position(x, y);
+            let floor = |dir| board.neighbour(pos, dir).is_some_and(|next| board.get_terrain(next).is_floor());
+            let joins = (floor(Direction::Up) && floor(Direction::Down)) || (floor(Direction::Left) && floor(Direction::Right));
+            if *board.get_terrain(pos) == BrickType::Wall && joins && rng.gen_bool(BRAID) {
+                board.set_terrain(pos, BrickType::None);
+            }
+        }
+    }
+}
+
+/// The wall cells, without the corners, on the side of a room that `dir`
+/// leads out through.
+fn room_side(Position(x0, y0): Position, Position(x1, y1): Position, dir: Direction) -> Vec<Position> {
+    match dir {
+        Direction::Up => (y0 + 1..y1).map(|y| Position(x0, y)).collect(),
+        Direction::Down => (y0 + 1..y1).map(|y| Position(x1, y)).collect(),
+        Direction::Left => (x0 + 1..x1).map(|x| Position(x, y0)).collect(),
+        Direction::Right => (x0 + 1..x1).map(|x| Position(x, y1)).collect(),
+    }
+}
+
+/// Splits the board into closed rooms and links them into a random tree,
+/// plus one extra link, with pairs of portals on random sides.
+fn rooms(board: &mut Board, rng: &mut StdRng) -> Option<()> {
+    let rows = rng.gen_range(1..=2);
+    let cols = rng.gen_range(2..=3);
+    let mut rooms = Vec::new();
+    for row in 0..rows {
+        for col in 0..cols {
+            let corner0 = Position(row * X_SIZE / rows, col * Y_SIZE / cols);
+            let corner1 = Position((row + 1) * X_SIZE / rows - 1, (col + 1) * Y_SIZE / cols - 1);
+            outline(board, corner0, corner1);
+            rooms.push((corner0, corner1));
+        }
+    }
+
+    let mut order: Vec<usize> = (0..rooms.len()).collect();
+    order.shuffle(rng);
+    let mut links: Vec<(usize, usize)> = (1..order.len())
+        .map(|i| (order[rng.gen_range(0..i)], order[i]))
+        .collect();
+    let extra = (rng.gen_range(0..rooms.len()), rng.gen_range(0..rooms.len()));
+    if extra.0 != extra.1 {
+        links.push(extra);
+    }
+
+    for (colour, (a, b)) in links.into_iter().enumerate() {
+        let (a0, a1) = rooms[a];
+        let (b0, b1) = rooms[b];
+        let out_a = *Direction::ALL.choose(rng).unwrap();
+        let out_b = *Direction::ALL.choose(rng).unwrap();
+        let free = |cells: Vec<Position>| -> Vec<Position> {
+            cells.into_iter().filter(|pos| *board.get_terrain(*pos) == BrickType::Wall).collect()
+        };
+        let cell_a = *free(room_side(a0, a1, out_a)).choose(rng)?;
+        let cell_b = *free(room_side(b0, b1, out_b)).choose(rng)?;
+        let colour = (colour % 7 + 1) as i16;
+        // Leaving A through `out_a` has to continue into B against `out_b`.
+        let rotation = (out_b.mirror() as u8 + 4 - out_a as u8) % 4;
+        board.set_terrain(cell_a, BrickType::Portal(Box::new(PortalData { destination: cell_b, colour, rotation })));
+        board.set_terrain(cell_b, BrickType::Portal(Box::new(PortalData { destination: cell_a, colour, rotation: (4 - rotation) % 4 })));
+    }
+    Some(())
+}
+
+/// Lays the snake on the straight run of floor closest to the centre that
+/// fits it and leaves a free cell ahead of its head.
+fn place_snake(board: &mut Board) -> Option<()> {
+    let fits = |x: usize, y: usize| (x - 1..x + SNAKE_LEN).all(|x| board.get_terrain(Position(x, y)).is_floor());
+    let Position(x, y) = (1..X_SIZE - SNAKE_LEN)
+        .flat_map(|x| (0..Y_SIZE).map(move |y| Position(x, y)))
+        .filter(|Position(x, y)| fits(*x, *y))
+        .min_by_key(|Position(x, y)| x.abs_diff(X_SIZE / 2) + y.abs_diff(Y_SIZE / 2))?;
+    for x in (x..x + SNAKE_LEN).rev() {
+        board[Position(x, y)] = BrickType::Snake(Direction::Up, Direction::Up);
+        board.snake.push_front(Position(x, y));
+    }
+    board.facing = Direction::Up;
+    board.last_step = Direction::Up;
+    Some(())
+}
+
+/// The cell a move from `pos` in `dir` ends on, following portals, or None
+/// when the move runs into a wall.
+fn move_target(board: &Board, pos: Position, dir: Direction) -> Option<Position> {
+    let (mut pos, mut dir) = (pos, dir);
+    for _ in 0..PORTAL_CHAIN {
+        let next = board.neighbour(pos, dir)?;
+        match board.get_terrain(next) {
+            BrickType::Portal(data) => {
+                dir = dir.rotate(data.rotation);
+                pos = data.destination;
+            },
+            terrain if terrain.is_floor() => return Some(next),
+            _ => return None,
+        }
+    }
+    None
+}
+
+fn reachable_cells(board: &Board, start: Position) -> HashSet<Position> {
+    let mut reached = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(pos) = queue.pop_front() {
+        for dir in Direction::ALL {
+            if let Some(next) = move_target(board, pos, dir) {
+                if reached.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_file;
+
+    fn food_cells(board: &Board) -> Vec<Position> {
+        (0..X_SIZE)
+            .flat_map(|x| (0..Y_SIZE).map(move |y| Position(x, y)))
+            .filter(|pos| board[*pos] == BrickType::Food)
+            .collect()
+    }
+
+    #[test]
+    fn every_floor_cell_is_reachable() {
+        for style in Style::ALL {
+            for seed in 0..20 {
+                let board = generate(style, seed);
+                assert_eq!(board.seed, Some(seed), "{} seed {} fell back", style.name(), seed);
+                let reachable = reachable_cells(&board, board.get_head());
+                for x in 0..X_SIZE {
+                    for y in 0..Y_SIZE {
+                        let pos = Position(x, y);
+                        assert!(
+                            !board.get_terrain(pos).is_floor() || reachable.contains(&pos),
+                            "{} seed {}: cell {} {} cannot be reached", style.name(), seed, x, y,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_board() {
+        for style in Style::ALL {
+            let (mut first, mut second) = (generate(style, 42), generate(style, 42));
+            assert_eq!(board_file::to_string(&first), board_file::to_string(&second));
+            assert!(food_cells(&first) == food_cells(&second));
+            first.place_food();
+            second.place_food();
+            assert!(food_cells(&first) == food_cells(&second));
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+use crate::board::*;
+use crate::board_file::{self, BoardBuilder};
+
+const HEADER: &str = "grid";
+const LEGEND: &str = "legend";
+
+// A grid file draws the board as one character per cell:
+//
+//   grid                  optional when the file ends in .grid
+//   #######
+//   #A.>>.#               # wall, . floor, ^ > v < snake segments
+//   #######               A-Z portals, other lower case letters portal exits
+//   legend
+//   A a 0 0 3             portal letter, destination letter, rotation, mirror, colour
+//   G 1 3 1 0 1           any bar line from the bar format
+//
+// The i-th cell of a portal letter, in reading order, leads to the i-th
+// cell of its destination, counted along the portal's direction turned
+// by its rotation, the same way a `P` bar lays out its destinations.
+
+pub fn has_header(lines: &[String]) -> bool {
+    lines.first().is_some_and(|line| line.trim() == HEADER)
+}
+
+fn is_marker(c: char) -> bool {
+    c.is_ascii_alphabetic() && Direction::from_arrow(c).is_none()
+}
+
+/// Orders the cells of a letter along `dir`.
+fn sort_along(cells: &mut [Position], dir: Direction) {
+    cells.sort_by_key(|Position(x, y)| {
+        let (x, y) = (*x as i64, *y as i64);
+        match dir {
+            Direction::Up => -x,
+            Direction::Right => y,
+            Direction::Down => x,
+            Direction::Left => -y,
+        }
+    });
+}
+
+fn add_portals(board: &mut Board, markers: &HashMap<char, Vec<Position>>, line: &str) -> Option<()> {
+    let mut iter = line.split_whitespace();
+    let source = iter.next()?.chars().next()?;
+    let target = iter.next()?.chars().next()?;
+    let rotation: u8 = iter.next()?.parse().ok()?;
+    let mirror: u8 = iter.next()?.parse().ok()?;
+    let colour = board_file::parse_colour(iter.next()?)?;
+    let rotation = rotation % 4;
+
+    let cells = markers.get(&source)?;
+    let mut destinations = markers.get(&target)?.clone();
+    if !source.is_ascii_uppercase() || cells.len() != destinations.len() {
+        return None;
+    }
+    let dir = match cells.as_slice() {
+        [Position(x0, _), Position(x1, _), ..] if x0 == x1 => Direction::Right,
+        _ => Direction::Down,
+    };
+    sort_along(&mut destinations, dir.rotate(rotation));
+    let out_rotation = if mirror != 0 { rotation + 2 } else { rotation };
+    for (cell, destination) in cells.iter().zip(destinations) {
+        board.set_terrain(*cell, BrickType::Portal(Box::new(PortalData {
+            destination,
+            colour,
+            rotation: out_rotation % 4,
+        })));
+    }
+    Some(())
+}
+
+/// Links the snake segments from the tail, the one no other arrow points
+/// at, to the head, the one pointing away from the snake.
+fn add_snake(board: &mut Board, segments: &HashMap<Position, Direction>) -> Option<()> {
+    if segments.is_empty() {
+        return Some(());
+    }
+    let pointed_at: Vec<Position> = segments.iter()
+        .filter_map(|(pos, dir)| board.neighbour(*pos, *dir))
+        .collect();
+    let mut tails = segments.keys().filter(|pos| !pointed_at.contains(pos));
+    let mut pos = *tails.next()?;
+    if tails.next().is_some() {
+        return None;
+    }
+    let mut order = VecDeque::new();
+    loop {
+        let dir = segments[&pos];
+        order.push_back((pos, dir));
+        match board.neighbour(pos, dir) {
+            Some(next) if segments.contains_key(&next) => pos = next,
+            _ => break,
+        }
+        if order.len() > segments.len() {
+            return None;
+        }
+    }
+    if order.len() != segments.len() {
+        return None;
+    }
+    let mut entered = order.front()?.1;
+    for (pos, dir) in order {
+        board[pos] = BrickType::Snake(entered, dir);
+        entered = dir;
+        board.snake.push_front(pos);
+        board.facing = dir;
+        board.last_step = dir;
+    }
+    Some(())
+}
+
+pub fn from_lines(lines: &[String]) -> Option<Board> {
+    let mut lines_iter = lines.iter().skip(has_header(lines) as usize);
+    let rows: Vec<Vec<char>> = lines_iter.by_ref()
+        .take_while(|line| line.trim() != LEGEND)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.chars().collect())
+        .collect();
+    let x_size = rows.len();
+    let y_size = rows.iter().map(|row| row.len()).max()?;
+
+    let mut builder = BoardBuilder::new(x_size, y_size);
+    let mut markers = HashMap::<char, Vec<Position>>::new();
+    let mut segments = HashMap::<Position, Direction>::new();
+    for (x, row) in rows.iter().enumerate() {
+        for (y, c) in row.iter().enumerate() {
+            let pos = Position(x, y);
+            match *c {
+                '#' => builder.board().set_terrain(pos, BrickType::Wall),
+                '.' | ' ' => {},
+                c if Direction::from_arrow(c).is_some() => { segments.insert(pos, Direction::from_arrow(c)?); },
+                c if is_marker(c) => markers.entry(c).or_default().push(pos),
+                _ => return None,
+            }
+        }
+    }
+
+    for line in lines_iter {
+        let mut iter = line.split_whitespace();
+        let second = iter.nth(1);
+        if second.is_some_and(|token| token.len() == 1 && is_marker(token.chars().next().unwrap())) {
+            add_portals(builder.board(), &markers, line)?;
+        } else if second.is_some() {
+            builder.add_bar(line.clone());
+        }
+    }
+    add_snake(builder.board(), &segments)?;
+    Some(builder.build())
+}
+
+/// Names every portal run with an upper case letter and every set of
+/// exits that are not portals themselves with a lower case one.
+fn assign_letters(board: &Board, runs: &[board_file::PortalRun]) -> Result<Vec<(char, char)>, String> {
+    let too_many = || "the board has too many portals for the grid format".to_owned();
+    let mut upper = ('A'..='Z').collect::<VecDeque<_>>();
+    let mut lower = ('a'..='z').filter(|c| is_marker(*c)).collect::<VecDeque<_>>();
+    let run_letters = runs.iter().map(|_| upper.pop_front()).collect::<Option<Vec<_>>>().ok_or_else(too_many)?;
+    let mut exit_letters = HashMap::<Vec<Position>, char>::new();
+
+    let mut letters = Vec::new();
+    for (run, letter) in runs.iter().zip(&run_letters) {
+        let mut destinations = run.destinations();
+        destinations.sort();
+        let target = runs.iter().position(|other| {
+            let mut cells = other.cells();
+            cells.sort();
+            cells == destinations
+        });
+        let target = match target {
+            Some(i) => run_letters[i],
+            None if destinations.iter().all(|pos| !matches!(board.get_terrain(*pos), BrickType::Portal(_))) => {
+                match exit_letters.get(&destinations) {
+                    Some(letter) => *letter,
+                    None => {
+                        let letter = lower.pop_front().ok_or_else(too_many)?;
+                        exit_letters.insert(destinations, letter);
+                        letter
+                    }
+                }
+            },
+            None => return Err("portals lead into part of another portal line".to_owned()),
+        };
+        letters.push((*letter, target));
+    }
+    Ok(letters)
+}
+
+/// Writes the board in the grid format. Bricks without a character of
+/// their own go to the legend as bars.
+pub fn to_string(board: &Board) -> Result<String, String> {
+    let mut grid = vec![vec!['.'; board.y_size()]; board.x_size()];
+    for (x, row) in grid.iter_mut().enumerate() {
+        for (y, c) in row.iter_mut().enumerate() {
+            if *board.get_terrain(Position(x, y)) == BrickType::Wall {
+                *c = '#';
+            }
+        }
+    }
+
+    let runs = board_file::portal_runs(board);
+    let letters = assign_letters(board, &runs)?;
+    let mut legend = String::new();
+    for (run, (letter, target)) in runs.iter().zip(&letters) {
+        for Position(x, y) in run.cells() {
+            grid[x][y] = *letter;
+        }
+        if target.is_ascii_lowercase() {
+            for Position(x, y) in run.destinations() {
+                if grid[x][y] != '.' && grid[x][y] != *target {
+                    return Err("a portal exit lies on a cell that is already drawn".to_owned());
+                }
+                grid[x][y] = *target;
+            }
+        }
+        let _ = writeln!(legend, "{} {} {} {} {}", letter, target, run.rotation, run.mirror as u8, run.colour);
+    }
+
+    let snake: Vec<Position> = board.snake.iter().cloned().collect();
+    for (i, Position(x, y)) in snake.iter().enumerate() {
+        let dir = if i == 0 {
+            board.facing
+        } else {
+            Direction::ALL.into_iter()
+                .find(|dir| board.neighbour(snake[i], *dir) == Some(snake[i - 1]))
+                .ok_or("the snake has to be made of adjacent segments")?
+        };
+        grid[*x][*y] = dir.arrow();
+    }
+
+    board_file::write_bar_runs(board, &mut legend, |brick| *brick == BrickType::Wall);
+    board_file::write_dynamic_walls(board, &mut legend);
+
+    let mut out = board.metadata.to_header() + HEADER + "\n";
+    for row in grid {
+        out.extend(row);
+        out.push('\n');
+    }
+    if !legend.is_empty() {
+        out = out + LEGEND + "\n" + &legend;
+    }
+    Ok(out)
+}
@@ -0,0 +1,111 @@
+use crate::basic_window::Dimensions;
+
+/// How much of a split an area takes.
+#[derive(Copy, Clone)]
+pub enum Size {
+    /// A number of lines or columns, cut down when the split runs out.
+    Fixed(i32),
+    /// A share of what the fixed areas leave, by weight.
+    Flex(i32),
+}
+
+#[derive(Copy, Clone)]
+pub enum Split {
+    /// Areas stacked from top to bottom.
+    Vertical,
+    /// Areas side by side from left to right.
+    Horizontal,
+}
+
+/// Divides `area` into one area for every size. Flexible areas share the
+/// room the fixed ones leave, the last of them taking what rounding left.
+pub fn split(area: &Dimensions, split: Split, sizes: &[Size]) -> Vec<Dimensions> {
+    let total = match split {
+        Split::Vertical => area.height(),
+        Split::Horizontal => area.width(),
+    };
+    let fixed: i32 = sizes.iter().map(|size| match size {
+        Size::Fixed(n) => (*n).max(0),
+        Size::Flex(_) => 0,
+    }).sum();
+    let weights: i32 = sizes.iter().map(|size| match size {
+        Size::Fixed(_) => 0,
+        Size::Flex(weight) => (*weight).max(0),
+    }).sum();
+    let flexible = (total - fixed).max(0);
+    let last_flex = sizes.iter().rposition(|size| matches!(size, Size::Flex(_)));
+
+    let mut start = 0;
+    let mut shared = 0;
+    sizes.iter().enumerate()
+        .map(|(i, size)| {
+            let extent = match size {
+                Size::Fixed(n) => (*n).max(0),
+                Size::Flex(_) if Some(i) == last_flex => flexible - shared,
+                Size::Flex(weight) if weights > 0 => flexible * (*weight).max(0) / weights,
+                Size::Flex(_) => 0,
+            };
+            if let Size::Flex(_) = size {
+                shared += extent;
+            }
+            let extent = extent.min(total - start);
+            let part = match split {
+                Split::Vertical => Dimensions::new(area.line() + start, area.column(), extent, area.width()),
+                Split::Horizontal => Dimensions::new(area.line(), area.column() + start, area.height(), extent),
+            };
+            start += extent;
+            part
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The start and extent of every area along the split.
+    fn spans(area: &Dimensions, split: Split, sizes: &[Size]) -> Vec<(i32, i32)> {
+        super::split(area, split, sizes).iter()
+            .map(|part| match split {
+                Split::Vertical => (part.line(), part.height()),
+                Split::Horizontal => (part.column(), part.width()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fixed_areas_follow_each_other() {
+        let area = Dimensions::new(2, 3, 10, 20);
+        assert_eq!(spans(&area, Split::Vertical, &[Size::Fixed(3), Size::Fixed(4)]), [(2, 3), (5, 4)]);
+        assert_eq!(spans(&area, Split::Horizontal, &[Size::Fixed(5), Size::Fixed(5)]), [(3, 5), (8, 5)]);
+    }
+
+    #[test]
+    fn flexible_areas_share_what_is_left_by_weight() {
+        let area = Dimensions::new(0, 0, 20, 20);
+        let sizes = [Size::Fixed(2), Size::Flex(1), Size::Flex(2), Size::Fixed(3)];
+        assert_eq!(spans(&area, Split::Vertical, &sizes), [(0, 2), (2, 5), (7, 10), (17, 3)]);
+    }
+
+    #[test]
+    fn the_last_flexible_area_takes_the_rounding() {
+        let area = Dimensions::new(0, 0, 10, 10);
+        let sizes = [Size::Flex(1), Size::Flex(1), Size::Flex(1)];
+        assert_eq!(spans(&area, Split::Horizontal, &sizes), [(0, 3), (3, 3), (6, 4)]);
+    }
+
+    #[test]
+    fn fixed_areas_are_cut_down_to_the_area() {
+        let area = Dimensions::new(0, 0, 5, 10);
+        let sizes = [Size::Fixed(3), Size::Flex(1), Size::Fixed(4), Size::Fixed(2)];
+        assert_eq!(spans(&area, Split::Vertical, &sizes), [(0, 3), (3, 0), (3, 2), (5, 0)]);
+    }
+
+    #[test]
+    fn areas_keep_the_other_dimension() {
+        let area = Dimensions::new(1, 2, 6, 8);
+        for part in split(&area, Split::Horizontal, &[Size::Flex(1), Size::Flex(3)]) {
+            assert_eq!((part.line(), part.height()), (1, 6));
+        }
+    }
+}
@@ -2,24 +2,82 @@ mod snake_window;
 mod basic_window;
 mod selection_window;
 mod board;
+mod camera;
 mod visuals;
+mod theme;
+mod colour;
 mod board_file;
+mod board_metadata;
+mod grid_file;
 mod game;
 mod event_emitter;
+mod campaign;
+mod rules;
+mod config;
+mod generator;
+mod daily;
+mod replay;
+mod menu;
+mod layout;
+mod widgets;
+mod crash;
 
 extern crate ncurses;
 
+use std::env;
+
 use game::Game;
+use generator::Style;
+
+/// Reads `--random [style] [seed]`, picking a random seed when none is given.
+fn parse_random(args: &[String]) -> Result<(Style, u64), String> {
+    let mut args = args.iter().peekable();
+    let style = match args.peek().and_then(|arg| Style::from_name(arg)) {
+        Some(style) => { args.next(); style },
+        None => Style::Obstacles,
+    };
+    let seed = match args.next() {
+        Some(arg) => arg.parse().map_err(|_| format!("'{}' is neither a style nor a seed", arg))?,
+        None => rand::random::<u32>() as u64,
+    };
+    match args.next() {
+        Some(arg) => Err(format!("unexpected argument '{}'", arg)),
+        None => Ok((style, seed)),
+    }
+}
 
 fn main() {
-    let context = basic_window::initialize();
-    if context.is_none() {
-        println!("Error: Could not initialize curses");
+    let args: Vec<String> = env::args().collect();
+    if args.len() == 4 && args[1] == "convert" {
+        if let Err(err) = board_file::convert(&args[2], &args[3]) {
+            println!("Error: {}", err);
+        }
+        return;
     }
-    let context = context.unwrap();
+    let random = match args.get(1).map(String::as_str) {
+        Some("--random") => match parse_random(&args[2..]) {
+            Ok(random) => Some(random),
+            Err(err) => {
+                println!("Error: {}", err);
+                return;
+            }
+        },
+        _ => None,
+    };
+
+    let context = match basic_window::initialize() {
+        Some(context) => context,
+        None => {
+            println!("Error: Could not initialize curses");
+            return;
+        }
+    };
     ncurses::keypad(ncurses::stdscr(), true);
     let mut game = Game::new(&context);
-    game.run();
+    match random {
+        Some((style, seed)) => game.run_random(style, seed),
+        None => game.run(),
+    }
 
     
 /*
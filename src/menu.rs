@@ -0,0 +1,102 @@
+/// An option of a menu, told apart by its id rather than its label.
+pub struct Entry<Id> {
+    pub id: Id,
+    pub label: String,
+    /// The line shown under the menu while the entry is highlighted.
+    pub description: String,
+    /// Lines shown under the menu while the entry is highlighted.
+    pub details: Vec<String>,
+    pub disabled: bool,
+}
+
+impl<Id> Entry<Id> {
+    pub fn new(id: Id, label: impl Into<String>) -> Entry<Id> {
+        Entry {
+            id,
+            label: label.into(),
+            description: String::new(),
+            details: Vec::new(),
+            disabled: false,
+        }
+    }
+
+    pub fn describe(mut self, description: impl Into<String>) -> Entry<Id> {
+        self.description = description.into();
+        self
+    }
+
+    pub fn with_details(mut self, details: Vec<String>) -> Entry<Id> {
+        self.details = details;
+        self
+    }
+
+    pub fn disable(mut self, disabled: bool) -> Entry<Id> {
+        self.disabled = disabled;
+        self
+    }
+}
+
+pub enum SettingValue {
+    Toggle(bool),
+    /// A number moved in steps between two bounds.
+    Slider { value: u64, min: u64, max: u64, step: u64, unit: &'static str },
+    /// One of a list of named choices.
+    Choice { choices: Vec<String>, selected: usize },
+}
+
+/// A value of the settings screen, changed in place.
+pub struct Setting<Id> {
+    pub id: Id,
+    pub label: &'static str,
+    pub value: SettingValue,
+}
+
+impl<Id> Setting<Id> {
+    pub fn new(id: Id, label: &'static str, value: SettingValue) -> Setting<Id> {
+        Setting { id, label, value }
+    }
+
+    /// Flips a toggle or moves to the next or previous value. Sliders
+    /// stop at their bounds, choices wrap around.
+    pub fn change(&mut self, forward: bool) {
+        match &mut self.value {
+            SettingValue::Toggle(on) => *on = !*on,
+            SettingValue::Slider { value, min, max, step, .. } => {
+                *value = if forward { (*value + *step).min(*max) } else { value.saturating_sub(*step).max(*min) };
+            },
+            SettingValue::Choice { choices, selected } => {
+                let len = choices.len().max(1);
+                *selected = if forward { (*selected + 1) % len } else { (*selected + len - 1) % len };
+            },
+        }
+    }
+
+    pub fn flag(&self) -> bool {
+        matches!(self.value, SettingValue::Toggle(true))
+    }
+
+    pub fn number(&self) -> u64 {
+        match self.value {
+            SettingValue::Slider { value, .. } => value,
+            _ => 0,
+        }
+    }
+
+    pub fn choice(&self) -> Option<&str> {
+        match &self.value {
+            SettingValue::Choice { choices, selected } => choices.get(*selected).map(String::as_str),
+            _ => None,
+        }
+    }
+
+    /// The label followed by the value, with the value starting at `column`.
+    pub fn text(&self, column: usize) -> String {
+        let value = match &self.value {
+            SettingValue::Toggle(on) => crate::rules::flag_name(*on).to_owned(),
+            SettingValue::Slider { value, unit: "", .. } => format!("< {} >", value),
+            SettingValue::Slider { value, unit, .. } => format!("< {} {} >", value, unit),
+            SettingValue::Choice { .. } => format!("< {} >", self.choice().unwrap_or_default()),
+        };
+        format!("{:width$}{}", self.label, value, width = column)
+    }
+}
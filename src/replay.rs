@@ -0,0 +1,96 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::board::{Board, Direction};
+
+pub const REPLAY_DIR: &str = "saves/replays";
+
+// A replay stores what is needed to play a run again on a seeded board:
+//
+//   board Random maze
+//   seed 20261019
+//   date 2026-10-19       only for daily challenges
+//   score 12
+//   input 5 1             tick and direction of every key press
+
+pub struct Replay {
+    pub board: String,
+    pub seed: Option<u64>,
+    pub date: Option<String>,
+    pub score: usize,
+    pub inputs: Vec<(u64, Direction)>,
+}
+
+impl Replay {
+    pub fn new(board: &Board) -> Replay {
+        Replay {
+            board: board.metadata.name.clone().unwrap_or_default(),
+            seed: board.seed,
+            date: None,
+            score: 0,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Notes a turn pressed before the step that makes `tick`.
+    pub fn record(&mut self, tick: u64, dir: Direction) {
+        self.inputs.push((tick, dir));
+    }
+
+    /// Reads a replay written by `save`.
+    pub fn load(path: &str) -> Option<Replay> {
+        let content = fs::read_to_string(path).ok()?;
+        let mut replay = Replay { board: String::new(), seed: None, date: None, score: 0, inputs: Vec::new() };
+        for line in content.lines() {
+            match line.split_once(' ') {
+                Some(("board", name)) => replay.board = name.to_owned(),
+                Some(("seed", seed)) => replay.seed = Some(seed.parse().ok()?),
+                Some(("date", date)) => replay.date = Some(date.to_owned()),
+                Some(("score", score)) => replay.score = score.parse().ok()?,
+                Some(("input", input)) => {
+                    let (tick, dir) = input.split_once(' ')?;
+                    replay.inputs.push((tick.parse().ok()?, Direction::from_primitive(dir.parse().ok()?)));
+                },
+                _ => {},
+            }
+        }
+        Some(replay)
+    }
+
+    /// The saved replays, newest first.
+    pub fn list() -> Vec<String> {
+        let mut paths: Vec<String> = match fs::read_dir(REPLAY_DIR) {
+            Ok(dir) => dir.filter_map(|entry| entry.ok()?.path().to_str().map(str::to_owned)).collect(),
+            Err(_) => Vec::new(),
+        };
+        paths.retain(|path| path.ends_with(".replay"));
+        paths.sort_by_cached_key(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok());
+        paths.reverse();
+        paths
+    }
+
+    pub fn save(&self, path: &str) {
+        if let Some(dir) = Path::new(path).parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(path, self.to_string());
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "board {}", self.board)?;
+        if let Some(seed) = self.seed {
+            writeln!(f, "seed {}", seed)?;
+        }
+        if let Some(date) = &self.date {
+            writeln!(f, "date {}", date)?;
+        }
+        writeln!(f, "score {}", self.score)?;
+        for (tick, dir) in &self.inputs {
+            writeln!(f, "input {} {}", tick, *dir as u8)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt::Write;
+
+#[derive(Copy, Clone)]
+pub struct GameRules {
+    /// Segments the snake grows by for every food eaten.
+    pub growth: usize,
+    pub self_collision: bool,
+    pub wall_collision: bool,
+    /// Foods kept on the board at the same time.
+    pub foods: usize,
+    pub wrap_around: bool,
+}
+
+impl Default for GameRules {
+    fn default() -> GameRules {
+        GameRules {
+            growth: 1,
+            self_collision: true,
+            wall_collision: true,
+            foods: 1,
+            wrap_around: false,
+        }
+    }
+}
+
+/// Rules set by a config file or board header, on top of other rules.
+#[derive(Clone, Default)]
+pub struct RuleOverrides {
+    pub growth: Option<usize>,
+    pub self_collision: Option<bool>,
+    pub wall_collision: Option<bool>,
+    pub foods: Option<usize>,
+    pub wrap_around: Option<bool>,
+}
+
+pub fn parse_flag(value: &str) -> Option<bool> {
+    match value {
+        "on" | "yes" | "true" => Some(true),
+        "off" | "no" | "false" => Some(false),
+        _ => None
+    }
+}
+
+pub fn flag_name(value: bool) -> &'static str {
+    if value { "on" } else { "off" }
+}
+
+impl From<GameRules> for RuleOverrides {
+    /// Overrides that set every rule.
+    fn from(rules: GameRules) -> RuleOverrides {
+        RuleOverrides {
+            growth: Some(rules.growth),
+            self_collision: Some(rules.self_collision),
+            wall_collision: Some(rules.wall_collision),
+            foods: Some(rules.foods),
+            wrap_around: Some(rules.wrap_around),
+        }
+    }
+}
+
+impl RuleOverrides {
+    /// Sets the rule named by `key`. Gives None when `key` is not a rule
+    /// and Some(false) when the value is not valid for it.
+    pub fn set(&mut self, key: &str, value: &str) -> Option<bool> {
+        let valid = match key {
+            "growth" => { self.growth = value.parse().ok(); self.growth.is_some() },
+            "self_collision" => { self.self_collision = parse_flag(value); self.self_collision.is_some() },
+            "wall_collision" => { self.wall_collision = parse_flag(value); self.wall_collision.is_some() },
+            "foods" => { self.foods = value.parse().ok().filter(|foods| *foods > 0); self.foods.is_some() },
+            "wrap_around" => { self.wrap_around = parse_flag(value); self.wrap_around.is_some() },
+            _ => return None
+        };
+        Some(valid)
+    }
+
+    pub fn apply(&self, rules: GameRules) -> GameRules {
+        GameRules {
+            growth: self.growth.unwrap_or(rules.growth),
+            self_collision: self.self_collision.unwrap_or(rules.self_collision),
+            wall_collision: self.wall_collision.unwrap_or(rules.wall_collision),
+            foods: self.foods.unwrap_or(rules.foods),
+            wrap_around: self.wrap_around.unwrap_or(rules.wrap_around),
+        }
+    }
+
+    /// Writes the rules that are set as `key: value` lines.
+    pub fn write(&self, out: &mut String) {
+        if let Some(growth) = self.growth {
+            let _ = writeln!(out, "growth: {}", growth);
+        }
+        if let Some(self_collision) = self.self_collision {
+            let _ = writeln!(out, "self_collision: {}", flag_name(self_collision));
+        }
+        if let Some(wall_collision) = self.wall_collision {
+            let _ = writeln!(out, "wall_collision: {}", flag_name(wall_collision));
+        }
+        if let Some(foods) = self.foods {
+            let _ = writeln!(out, "foods: {}", foods);
+        }
+        if let Some(wrap_around) = self.wrap_around {
+            let _ = writeln!(out, "wrap_around: {}", flag_name(wrap_around));
+        }
+    }
+}
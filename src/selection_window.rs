@@ -6,11 +6,16 @@ use crate::visuals::*;
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub enum SelectionWindowEventId {
-    SelectId,
+    Select,
+    Cancel,
+    HighlightChanged,
 }
 
 pub enum SelectionWindowEvent {
-    Select(String)
+    Select(usize),
+    /// Escape or backspace was pressed to leave the menu.
+    Cancel,
+    HighlightChanged(usize),
 }
 
 impl Event for SelectionWindowEvent {
@@ -18,7 +23,9 @@ impl Event for SelectionWindowEvent {
 
     fn id(&self) -> Self::EventId {
         match *self {
-            SelectionWindowEvent::Select(_) => SelectionWindowEventId::SelectId,
+            SelectionWindowEvent::Select(_) => SelectionWindowEventId::Select,
+            SelectionWindowEvent::Cancel => SelectionWindowEventId::Cancel,
+            SelectionWindowEvent::HighlightChanged(_) => SelectionWindowEventId::HighlightChanged,
         }
     }
 }
@@ -27,7 +34,14 @@ pub struct SelectionWindow {
     win: BasicWindow,
     event_pool: EventPool<SelectionWindowEvent>,
     options: Vec<String>,
+    disabled: Vec<bool>,
+    /// One line shown under the options for the highlighted one.
+    descriptions: Vec<String>,
     selection: Option<usize>,
+    /// The first option in view when the list is longer than the window.
+    top: usize,
+    /// Whether the highlighted option shows as the one keys act on.
+    focused: bool,
     common_visuals: Rc<CommonVisuals>,
 }
 
@@ -43,25 +57,143 @@ impl SelectionWindow {
     }
 
     pub fn new_selected(context: &NcursesContext, dim: Dimensions, opts: Vec<String>, sel: Option<usize>) -> SelectionWindow {
-        SelectionWindow {
+        let mut win = SelectionWindow {
             win: BasicWindow::new(dim),
             event_pool: EventPool::new(),
+            disabled: vec![false; opts.len()],
+            descriptions: Vec::new(),
             options: opts,
             selection: sel,
+            top: 0,
+            focused: true,
             common_visuals: Rc::clone(&context.get_visuals().common_visuals),
+        };
+        win.scroll_to_selection();
+        win
+    }
+
+    /// Disabled options are drawn greyed out and skipped when moving the
+    /// selection.
+    pub fn set_disabled(&mut self, i: usize, disabled: bool) {
+        self.disabled[i] = disabled;
+    }
+
+    /// Replaces the text of option `i` and redraws it.
+    pub fn set_option(&mut self, i: usize, option: String) {
+        self.options[i] = option;
+        if i >= self.top && i < self.top + self.rows() {
+            self.win.move_cur((i - self.top + 1) as i32, 0);
+            self.win.clear_to_end_of_line();
+            self.draw_option(i);
         }
     }
 
+    /// Gives every option a description line. Makes room for it at the
+    /// bottom of the window.
+    pub fn set_descriptions(&mut self, descriptions: Vec<String>) {
+        self.descriptions = descriptions;
+        self.scroll_to_selection();
+    }
+
+    /// The number of options the window shows at once.
+    fn rows(&self) -> usize {
+        let reserved = 2 + !self.descriptions.is_empty() as i32;
+        (self.win.height() - reserved).max(1) as usize
+    }
+
+    /// Scrolls so the selection is in view. Returns whether it scrolled.
+    fn scroll_to_selection(&mut self) -> bool {
+        let sel = match self.selection {
+            Some(sel) => sel,
+            None => return false,
+        };
+        let top = self.top.min(sel).max((sel + 1).saturating_sub(self.rows()));
+        let scrolled = top != self.top;
+        self.top = top;
+        scrolled
+    }
+
+    /// The next enabled option after the selection that starts with `c`.
+    fn find_hotkey(&self, c: char) -> Option<usize> {
+        let len = self.options.len();
+        let start = self.selection.map_or(0, |sel| sel + 1);
+        (start..start + len)
+            .map(|i| i % len)
+            .find(|i| !self.disabled[*i] && self.options[*i].chars().next().is_some_and(|first| first.eq_ignore_ascii_case(&c)))
+    }
+
+    fn step_selection(&self, sel: usize, forward: bool) -> usize {
+        let len = self.options.len();
+        let mut next = sel;
+        for _ in 0..len {
+            next = if forward { (next + 1) % len } else { (next + len - 1) % len };
+            if !self.disabled[next] {
+                return next;
+            }
+        }
+        sel
+    }
+
     fn draw_option(&self, i: usize) {
-        let mut attr: Attributes = self.common_visuals.get_colour(ncurses::COLOR_WHITE).into();
+        let mut attr = self.common_visuals.get_colour(ncurses::COLOR_WHITE);
+        if self.disabled[i] {
+            attr = attr | ncurses::A_DIM();
+        }
         if self.selection.is_some() && self.selection.unwrap() == i {
-            attr = attr | ncurses::A_REVERSE();
+            attr = attr | if self.focused { ncurses::A_REVERSE() } else { ncurses::A_BOLD() };
+        }
+        if i < self.top || i >= self.top + self.rows() {
+            return;
         }
         self.win.apply_attr(attr);
-        self.win.move_print((i + 1) as i32, 2, &self.options[i]);
+        self.win.move_print((i - self.top + 1) as i32, 2, &self.options[i]);
         self.win.clear_attr();
     }
 
+    /// Marks the ends of a list that goes on past the window.
+    fn draw_indicators(&self) {
+        let more_below = self.top + self.rows() < self.options.len();
+        self.win.move_print(0, 2, if self.top > 0 { "^ more" } else { "      " });
+        self.win.move_print(self.rows() as i32 + 1, 2, if more_below { "v more" } else { "      " });
+    }
+
+    fn draw_description(&self) {
+        if self.descriptions.is_empty() {
+            return;
+        }
+        let line = self.rows() as i32 + 2;
+        self.win.move_cur(line, 0);
+        self.win.clear_to_end_of_line();
+        let description = self.selection.and_then(|sel| self.descriptions.get(sel));
+        self.win.move_print(line, 2, description.map_or("", String::as_str));
+    }
+
+    fn select(&mut self, next: usize) {
+        let sel = self.selection;
+        self.selection = Some(next);
+        if sel == self.selection {
+            return;
+        }
+        if self.scroll_to_selection() {
+            self.draw();
+        } else {
+            if let Some(sel) = sel {
+                self.draw_option(sel);
+            }
+            self.draw_option(next);
+            self.draw_description();
+        }
+        self.event_pool.push_event(SelectionWindowEvent::HighlightChanged(next));
+    }
+
+    pub fn height(&self) -> i32 {
+        self.win.height()
+    }
+
+    pub fn get_selection(&self) -> Option<usize> {
+        self.selection
+    }
+
     pub fn get_selected(&self) -> Option<&String> {
         Some(&self.options[self.selection?])
     }
@@ -73,32 +205,56 @@ impl Window for SelectionWindow {
         self.win.refresh();
     }
 
+    /// Arrows move the selection and letters jump to the next option
+    /// starting with them. Other keys are ignored.
     fn handle_keypress(&mut self, key: i32) {
-        if self.selection.is_none() {
-            if !self.options.is_empty() {
-                self.selection = Some(0);
-                self.draw_option(0)
-            }
-        } else {
-            let sel = self.selection.unwrap();
-            match key {
-                ncurses::KEY_UP => { self.selection = if sel == 0 {Some(self.options.len() - 1)} else {Some(sel - 1)} },
-                ncurses::KEY_DOWN => { self.selection = Some((sel + 1) % self.options.len()) },
-                10 /*NEWLINE*/ => { self.event_pool.push_event(SelectionWindowEvent::Select(self.options[sel].clone())); }
-                _ => { println!("{}", key); ncurses::getch(); panic!(); }
-            }
-            if sel != self.selection.unwrap() {
-                self.draw_option(sel);
-                self.draw_option(self.selection.unwrap());
+        if key == KEY_ESCAPE || key == KEY_DELETE || key == ncurses::KEY_BACKSPACE {
+            self.event_pool.push_event(SelectionWindowEvent::Cancel);
+            return;
+        }
+        if self.options.is_empty() {
+            return;
+        }
+        let sel = match self.selection {
+            Some(sel) => sel,
+            None => {
+                self.select(0);
+                return;
             }
+        };
+        match key {
+            ncurses::KEY_UP => self.select(self.step_selection(sel, false)),
+            ncurses::KEY_DOWN => self.select(self.step_selection(sel, true)),
+            KEY_NEWLINE => {
+                if !self.disabled[sel] {
+                    self.event_pool.push_event(SelectionWindowEvent::Select(sel));
+                }
+            },
+            key => {
+                let hotkey = char::from_u32(key as u32).filter(char::is_ascii_alphanumeric);
+                if let Some(next) = hotkey.and_then(|c| self.find_hotkey(c)) {
+                    self.select(next);
+                }
+            },
         }
     }
 
     fn draw(&self) {
-        for i in 0..self.options.len() {
+        self.win.erase();
+        for i in self.top..self.options.len().min(self.top + self.rows()) {
             self.draw_option(i);
         }
+        self.draw_indicators();
+        self.draw_description();
         self.refresh();
     }
 
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn set_focus(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
 }
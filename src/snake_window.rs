@@ -2,112 +2,520 @@ use std::cell::Ref;
 
 use crate::basic_window::*;
 use crate::board::*;
+use crate::camera::Camera;
+use crate::campaign::Target;
+use crate::crash;
+use crate::replay::Replay;
 use crate::visuals::*;
 
+const PANEL_WIDTH: i32 = 16;
+/// Characters of an info line that fit in the panel, inside the space
+/// on either side of it.
+const INFO_WIDTH: usize = PANEL_WIDTH as usize - 3;
+/// Rows under the board for the ending message.
+const FOOTER_HEIGHT: i32 = 2;
+/// The smallest view that is still playable.
+const MIN_VIEW: (usize, usize) = (5, 10);
+const MINIMAP_SIZE: (usize, usize) = (6, 12);
+
+/// Letter keys that steer the snake besides the arrows.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum KeyLayout {
+    Arrows,
+    Vim,
+    Numpad,
+}
+
+impl KeyLayout {
+    pub const ALL: [KeyLayout; 3] = [KeyLayout::Arrows, KeyLayout::Vim, KeyLayout::Numpad];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyLayout::Arrows => "arrows",
+            KeyLayout::Vim => "vim",
+            KeyLayout::Numpad => "numpad",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<KeyLayout> {
+        KeyLayout::ALL.into_iter().find(|layout| layout.name() == name)
+    }
+
+    /// The direction a key steers in, with up, right, down and left on
+    /// k, l, j, h for vim and on 8, 6, 2, 4 for the numpad.
+    pub fn direction(&self, key: i32) -> Option<Direction> {
+        let keys = match self {
+            KeyLayout::Arrows => return None,
+            KeyLayout::Vim => ['k', 'l', 'j', 'h'],
+            KeyLayout::Numpad => ['8', '6', '2', '4'],
+        };
+        keys.iter().position(|c| *c as i32 == key).map(|i| Direction::ALL[i])
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum GameState {
+    Running,
+    Lost,
+    Won,
+}
+
 pub struct SnakeWindow<'a> {
     win: BasicWindow,
     board: Board,
     visuals: Ref<'a, SnakeVisuals>,
-    lost: bool,
+    state: GameState,
+    target: Option<Target>,
+    info: Vec<String>,
+    replay: Replay,
+    /// The screen size the window was laid out for.
+    screen: (i32, i32),
+    camera: Camera,
+    /// Draws the body with box drawing lines instead of arrows.
+    smooth_snake: bool,
+    keys: KeyLayout,
 }
 
 impl SnakeWindow<'_> {
 
-    pub fn new_default(context: &NcursesContext, x_size: usize, y_size: usize) -> SnakeWindow {
-        Self::new(context, Board::new_default(x_size, y_size))
-    }
-
-    pub fn new(context: &NcursesContext, board: Board) -> SnakeWindow {
-        SnakeWindow {
-            win: BasicWindow::new(Dimensions::new(0, 0, board.x_size() as i32 + 2, board.y_size() as i32 + 8)),
+    pub fn new(context: &NcursesContext, mut board: Board) -> SnakeWindow {
+        board.fill_food();
+        let replay = Replay::new(&board);
+        let camera = Camera::new(board.x_size(), board.y_size());
+        let mut win = SnakeWindow {
+            win: BasicWindow::new(Dimensions::centred(board.x_size() as i32 + FOOTER_HEIGHT, board.y_size() as i32 + PANEL_WIDTH)),
             board,
             visuals: context.get_visuals().snake_visuals.borrow(),
-            lost: false,
+            state: GameState::Running,
+            target: None,
+            info: Vec::new(),
+            replay,
+            screen: screen_size(),
+            camera,
+            smooth_snake: false,
+            keys: KeyLayout::Arrows,
+        };
+        win.place();
+        win
+    }
+
+    /// The smallest height and width the window can be shown at.
+    fn min_size(&self) -> (i32, i32) {
+        (
+            self.board.x_size().min(MIN_VIEW.0).div_ceil(self.camera.rows_per_line) as i32 + FOOTER_HEIGHT,
+            (self.board.y_size().min(MIN_VIEW.1) * self.camera.columns_per_cell) as i32 + PANEL_WIDTH,
+        )
+    }
+
+    fn fits(&self) -> bool {
+        let (height, width) = self.min_size();
+        self.screen.0 >= height && self.screen.1 >= width
+    }
+
+    /// Sizes the view to the screen and centres the window.
+    fn place(&mut self) {
+        self.screen = screen_size();
+        if !self.fits() {
+            return;
         }
+        self.camera.resize((self.screen.0 - FOOTER_HEIGHT) as usize, (self.screen.1 - PANEL_WIDTH) as usize);
+        self.camera.follow(self.board.get_head());
+        let (height, width) = (self.camera.lines() as i32 + FOOTER_HEIGHT, self.camera.columns() as i32 + PANEL_WIDTH);
+        self.win = BasicWindow::new(Dimensions::centred(height, width));
+    }
+
+    /// Lays the window out for the current screen and redraws everything.
+    fn layout(&mut self) {
+        clear_screen();
+        self.place();
+        self.draw();
+    }
+
+    /// Switches between one board row per line and two rows per line drawn
+    /// with half blocks. Needs a terminal with colour.
+    pub fn set_half_blocks(&mut self, on: bool) {
+        let on = on && self.visuals.common_visuals.has_colour();
+        self.camera.rows_per_line = if on { 2 } else { 1 };
+        self.layout();
+    }
+
+    /// Switches between one and two terminal columns per board cell. Two
+    /// columns make cells about square.
+    pub fn set_square_cells(&mut self, on: bool) {
+        self.camera.columns_per_cell = if on { 2 } else { 1 };
+        self.layout();
+    }
+
+    pub fn set_smooth_snake(&mut self, on: bool) {
+        self.smooth_snake = on;
+        self.draw_board();
+    }
+
+    pub fn set_keys(&mut self, keys: KeyLayout) {
+        self.keys = keys;
     }
 
-    fn change_brick(&mut self, Position(x, y): Position, category: BrickType) {
-        let visual = self.visuals.get(&category);
-        self.board[x][y] = category;
-        self.win.move_put(x as i32, y as i32, visual);
+    pub fn set_target(&mut self, target: Target) {
+        self.target = Some(target);
+    }
+
+    /// Extra lines shown in the side panel under the score.
+    pub fn set_info(&mut self, info: Vec<String>) {
+        self.info = info;
+    }
+
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn score(&self) -> usize {
+        self.board.score()
+    }
+
+    /// The inputs of the run so far, with the current score.
+    pub fn replay(&mut self) -> &mut Replay {
+        self.replay.score = self.board.score();
+        &mut self.replay
+    }
+
+    /// Writes the board and the run so far to a crash dump and returns
+    /// the dump's path.
+    pub fn save_crash_dump(&mut self) -> Option<String> {
+        self.replay.score = self.board.score();
+        crash::save(&self.board, &self.replay)
+    }
+
+    fn change_brick(&mut self, pos: Position, category: BrickType) {
+        self.board[pos] = category;
+        self.draw_brick(pos);
+    }
+
+    fn change_terrain(&mut self, pos: Position, category: BrickType) {
+        self.board.set_terrain(pos, category);
+        self.draw_brick(pos);
+    }
+
+    fn draw_brick(&self, pos: Position) {
+        if let Some((line, col)) = self.camera.to_view(pos) {
+            self.draw_cell(line, col);
+        }
+    }
+
+    /// Draws what is shown on screen line `line` in column `col` of the
+    /// view: one board cell, or two stacked ones as a half block.
+    fn draw_cell(&self, line: usize, col: usize) {
+        let scale = self.camera.columns_per_cell;
+        let (x, y) = (line as i32, (col * scale) as i32);
+        if self.camera.rows_per_line == 2 {
+            let top = self.camera.to_board(2 * line, col);
+            let bottom = (2 * line + 1 < self.camera.height).then(|| self.camera.to_board(2 * line + 1, col));
+            let pair = self.visuals.get_pair(self.board.get_visible(top), bottom.map(|pos| self.board.get_visible(pos)));
+            if let Some(ch) = pair {
+                self.win.move_cur(x, y);
+                for _ in 0..scale {
+                    self.win.put_wide(ch);
+                }
+            }
+        } else {
+            let pos = self.camera.to_board(line, col);
+            let brick = self.board.get_visible(pos);
+            let tail = self.board.snake.back() == Some(&pos);
+            let smooth = self.smooth_snake.then(|| self.visuals.get_smooth(brick, tail)).flatten();
+            if let Some(smooth) = smooth {
+                self.win.move_cur(x, y);
+                for ch in &smooth[..scale] {
+                    self.win.put_wide(*ch);
+                }
+            } else if scale == 2 {
+                let [left, right] = self.visuals.get_double(brick);
+                self.win.move_put(x, y, left);
+                self.win.put_character(right);
+            } else {
+                self.win.move_put(x, y, self.visuals.get(brick));
+            }
+        }
+    }
+
+    fn draw_board(&self) {
+        for line in 0..self.camera.lines() {
+            for col in 0..self.camera.width {
+                self.draw_cell(line, col);
+            }
+        }
+    }
+
+    fn panel_column(&self) -> i32 {
+        self.camera.columns() as i32 + 1
     }
 
     fn turn(&mut self, dir: Direction) {
+        if *self.board.get_terrain(self.board.get_head()) == BrickType::Ice {
+            return;
+        }
         if (dir as u8) != (self.board.last_step as u8 + 2) % 4 {
             self.board.facing = dir;
             self.change_brick(self.board.get_head(), BrickType::SnakeHead(dir));
         }
     }
 
-    fn step_snake(&mut self, new_pos: Position, grow: bool) {
-        if !grow {
+    /// Moves the head to `new_pos`, which it entered in `dir` after leaving
+    /// its old cell in `left`.
+    fn step_snake(&mut self, new_pos: Position, left: Direction, dir: Direction) {
+        if self.board.pending_growth > 0 {
+            self.board.pending_growth -= 1;
+        } else {
             let back = self.board.snake.pop_back().unwrap();
-            self.change_brick(back, BrickType::None);
+            if !self.board.snake.contains(&back) {
+                self.change_brick(back, BrickType::None);
+            }
+            if let Some(tail) = self.board.snake.back() {
+                self.draw_brick(*tail);
+            }
         }
 
         self.board.snake.push_front(new_pos);
         self.board.snake_pos = new_pos;
+        let entered = std::mem::replace(&mut self.board.head_entered, dir);
         self.change_brick(new_pos, BrickType::SnakeHead(self.board.facing));
-        self.change_brick(self.board.snake[1], BrickType::Snake(self.board.facing));
+        self.change_brick(self.board.snake[1], BrickType::Snake(entered, left));
+
+        if let BrickType::Switch(group) = *self.board.get_terrain(new_pos) {
+            self.toggle_wall_group(group);
+        }
+    }
+
+    fn toggle_wall_group(&mut self, group: usize) {
+        let solid = &mut self.board.wall_groups[group].solid;
+        *solid = !*solid;
+        self.settle_wall_group(group);
+    }
+
+    /// Brings the group's cells in line with its state. A wall that should
+    /// close stays open while anything occupies its cell and is retried on
+    /// every following tick.
+    fn settle_wall_group(&mut self, group: usize) {
+        let solid = self.board.wall_groups[group].solid;
+        for i in 0..self.board.wall_groups[group].cells.len() {
+            let pos = self.board.wall_groups[group].cells[i];
+            if *self.board.get_terrain(pos) == BrickType::GroupWall(group, solid) {
+                continue;
+            }
+            if solid && self.board[pos] != BrickType::None {
+                continue;
+            }
+            self.change_terrain(pos, BrickType::GroupWall(group, solid));
+        }
     }
 
     fn spawn_food(&mut self) {
-        let pos = self.board.find_valid_food_spawn();
-        if pos.is_some() {
-            self.change_brick(pos.unwrap(), BrickType::Food);
+        if let Some(pos) = self.board.place_food() {
+            self.draw_brick(pos);
+        }
+    }
+
+    fn collect_key(&mut self, colour: i16) {
+        self.board.keys.insert(colour);
+        for pos in self.board.find_doors(colour) {
+            self.change_terrain(pos, BrickType::None);
         }
     }
 
     fn draw_points(&self) {
-        let points_str = format!(" {} ", self.board.snake.len() - self.board.initial_size);
-        self.win.set_attr(self.visuals.colors_points.into());
-        self.win.move_print(2, self.board.y_size() as i32 + 1, &points_str);
+        let points_str = format!(" {} ", self.board.score());
+        self.win.set_attr(self.visuals.colors_points);
+        self.win.move_print(2, self.panel_column(), &points_str);
+        self.win.clear_attr();
+    }
+
+    /// The seed, with its digits on lines of their own, then the info
+    /// lines, all cut to the width of the panel.
+    fn info_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(seed) = self.board.seed {
+            let digits: Vec<char> = seed.to_string().chars().collect();
+            lines.push("seed".to_owned());
+            lines.extend(digits.chunks(INFO_WIDTH).map(|chunk| chunk.iter().collect()));
+        }
+        lines.extend(self.info.iter().map(|line| line.chars().take(INFO_WIDTH).collect()));
+        lines
+    }
+
+    fn draw_info(&self) {
+        self.win.set_attr(self.visuals.colors_points);
+        for (i, line) in self.info_lines().iter().enumerate() {
+            self.win.move_print(4 + i as i32, self.panel_column(), &format!(" {} ", line));
+        }
         self.win.clear_attr();
     }
 
+    /// Shows the whole board in the side panel, under the info lines, with
+    /// the part in view highlighted.
+    fn draw_minimap(&self) {
+        if !self.camera.is_scrolling() {
+            return;
+        }
+        let top = 5 + self.info_lines().len() as i32;
+        let map = self.camera.minimap(MINIMAP_SIZE.0, MINIMAP_SIZE.1);
+        for (i, row) in map.iter().enumerate() {
+            self.win.move_cur(top + i as i32, self.panel_column() + 1);
+            for in_view in row {
+                let attr: Attributes = if *in_view { self.visuals.colors_points } else { Attributes::none() };
+                self.win.put_character(PrintableCharacter::new(if *in_view { '#' } else { '.' }, attr));
+            }
+        }
+    }
+
     pub fn draw_ending_message(&self) {
-        let game_over = format!(" game over, your score: {} ", self.board.snake.len() - self.board.initial_size);
-        self.win.set_attr(self.visuals.colors_ending.into());
-        self.win.move_print(self.board.x_size() as i32, 0, &game_over);
-        self.win.move_print(self.board.x_size() as i32 + 1, 0, " press any key to continue ");
+        let game_over = match self.state {
+            GameState::Won => format!(" level complete, your score: {} ", self.board.score()),
+            _ => format!(" game over, your score: {} ", self.board.score()),
+        };
+        self.win.set_attr(self.visuals.colors_ending);
+        self.win.move_print(self.camera.lines() as i32, 0, &game_over);
+        self.win.move_print(self.camera.lines() as i32 + 1, 0, " press any key to continue ");
         self.win.clear_attr();
     }
 
     fn try_step(&mut self) -> bool {
         self.board.last_step = self.board.facing;
-        let new_pos = self.board.get_head().move_dir(self.board.last_step);
+        self.try_move(self.board.facing)
+    }
 
-        let brick = &self.board.board[new_pos.0][new_pos.1];
-        match brick {
-            BrickType::None => {
-                self.step_snake(new_pos, false);
-                true
-            },
-            BrickType::Wall => false,
-            BrickType::Snake(_) => false,
-            BrickType::SnakeHead(_) => false,
+    fn try_move(&mut self, dir: Direction) -> bool {
+        self.try_move_from(dir, dir)
+    }
+
+    /// Moves the head on in `dir` after it left its cell in `left`. The
+    /// two differ once a portal has turned the snake.
+    fn try_move_from(&mut self, left: Direction, dir: Direction) -> bool {
+        let new_pos = match self.board.step_from(self.board.get_head(), dir) {
+            Some(new_pos) => new_pos,
+            None => return false,
+        };
+
+        match self.board[new_pos] {
+            BrickType::Snake(..) | BrickType::SnakeHead(_) if self.board.rules.self_collision => return false,
             BrickType::Food => {
-                self.step_snake(new_pos, true);
+                self.board.food_eaten += 1;
+                self.board.pending_growth += self.board.rules.growth;
+                self.step_snake(new_pos, left, dir);
                 self.draw_points();
                 self.spawn_food();
-                true
+                return true;
+            },
+            BrickType::Key(colour) => {
+                self.step_snake(new_pos, left, dir);
+                self.collect_key(colour);
+                return true;
             },
+            _ => {}
+        }
+
+        match self.board.get_terrain(new_pos) {
             BrickType::Portal(data) => {
+                let rotation = data.rotation;
                 self.board.snake_pos = data.destination;
-                self.board.facing = Direction::from_primitive(self.board.facing as u8 + data.rotation);
-                self.try_step()
+                self.board.facing = self.board.facing.rotate(rotation);
+                self.board.last_step = self.board.facing;
+                self.try_move_from(left, dir.rotate(rotation))
+            },
+            terrain if terrain.is_floor() || *terrain == BrickType::Gate(dir) || !self.board.rules.wall_collision => {
+                self.step_snake(new_pos, left, dir);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Removes the snake from `pos` to its tail. Returns false when `pos`
+    /// is the head, which means the snake cannot survive the cut.
+    fn cut_snake(&mut self, pos: Position) -> bool {
+        let index = self.board.snake.iter().position(|segment| *segment == pos);
+        match index {
+            Some(0) => false,
+            Some(i) => {
+                for segment in self.board.snake.split_off(i) {
+                    self.change_brick(segment, BrickType::None);
+                }
+                self.draw_brick(self.board.snake[i - 1]);
+                self.draw_points();
+                true
+            },
+            None => true,
+        }
+    }
+
+    /// Moves timed and moving walls to their position for the next tick.
+    /// A wall landing on the head kills the snake; one landing on the body
+    /// cuts off everything from that segment to the tail. Food under a wall
+    /// is crushed and respawned, keys keep their cell free.
+    fn advance_walls(&mut self) -> bool {
+        self.board.tick += 1;
+        let mut alive = true;
+        let mut crushed_foods = 0;
+        for i in 0..self.board.dynamic_walls.len() {
+            for pos in std::mem::take(&mut self.board.dynamic_walls[i].cells) {
+                if *self.board.get_terrain(pos) == BrickType::DynamicWall {
+                    self.change_terrain(pos, BrickType::None);
+                }
+            }
+        }
+        for i in 0..self.board.dynamic_walls.len() {
+            let mut cells = self.board.dynamic_walls[i].cells_at(&self.board, self.board.tick);
+            let harmful = self.board.rules.wall_collision;
+            cells.retain(|pos| {
+                *self.board.get_terrain(*pos) == BrickType::None && !matches!(self.board[*pos], BrickType::Key(_))
+            });
+            for pos in &cells {
+                match self.board[*pos] {
+                    BrickType::Snake(..) | BrickType::SnakeHead(_) if harmful => alive &= self.cut_snake(*pos),
+                    BrickType::Food => {
+                        self.change_brick(*pos, BrickType::None);
+                        crushed_foods += 1;
+                    },
+                    _ => {}
+                }
+                self.change_terrain(*pos, BrickType::DynamicWall);
             }
+            self.board.dynamic_walls[i].cells = cells;
+        }
+        for _ in 0..crushed_foods {
+            self.spawn_food();
+        }
+        alive
+    }
+
+    fn apply_terrain(&mut self) -> bool {
+        match *self.board.get_terrain(self.board.get_head()) {
+            BrickType::Conveyor(dir) => self.try_move(dir),
+            _ => true,
         }
     }
 
-    pub fn step(&mut self) -> bool {
-        if !self.lost {
-            if !self.try_step() {
+    /// Advances the game by a tick. The game stays paused while the
+    /// terminal is too small to show the board.
+    pub fn step(&mut self) -> GameState {
+        if self.screen != screen_size() {
+            self.layout();
+        }
+        if self.state == GameState::Running && self.fits() {
+            if !self.try_step() || !self.apply_terrain() || !self.advance_walls() {
+                self.state = GameState::Lost;
+            } else if self.target.is_some_and(|target| target.reached(&self.board)) {
+                self.state = GameState::Won;
+            }
+            for group in 0..self.board.wall_groups.len() {
+                self.settle_wall_group(group);
+            }
+            if self.camera.follow(self.board.get_head()) {
+                self.draw_board();
+                self.draw_minimap();
+            }
+            if self.state != GameState::Running {
                 self.draw_ending_message();
-                self.lost = true;
             }
         }
-        self.lost
+        self.state
     }
 
 }
@@ -118,25 +526,47 @@ impl Window for SnakeWindow<'_> {
     }
 
     fn draw(&self) {
-        for i in 0..self.board.x_size() {
-            self.win.move_cur(i as i32, 0);
-            for brick in &self.board[i] {
-                self.win.put_character(self.visuals.get(brick));
-            }
+        if !self.fits() {
+            let (height, width) = self.min_size();
+            show_message(&format!("terminal too small, need {}x{}", width, height));
+            return;
         }
+        self.draw_board();
         self.draw_points();
+        self.draw_info();
+        self.draw_minimap();
+        if self.state != GameState::Running {
+            self.draw_ending_message();
+        }
         self.win.refresh();
     }
 
     fn handle_keypress(&mut self, key: i32) {
-        if !self.lost {
-            match key {
-                ncurses::KEY_UP    => { self.turn(Direction::Up);    }
-                ncurses::KEY_RIGHT => { self.turn(Direction::Right); }
-                ncurses::KEY_DOWN  => { self.turn(Direction::Down);  }
-                ncurses::KEY_LEFT  => { self.turn(Direction::Left);  }
-                _ => {}
-            }
+        if key == ncurses::KEY_RESIZE {
+            self.layout();
+        }
+        if key == 'z' as i32 {
+            self.set_half_blocks(self.camera.rows_per_line == 1);
+        }
+        if key == 's' as i32 {
+            self.set_square_cells(self.camera.columns_per_cell == 1);
+        }
+        if key == 'b' as i32 {
+            self.set_smooth_snake(!self.smooth_snake);
+        }
+        if self.state == GameState::Running && self.fits() {
+            let dir = match key {
+                ncurses::KEY_UP    => Direction::Up,
+                ncurses::KEY_RIGHT => Direction::Right,
+                ncurses::KEY_DOWN  => Direction::Down,
+                ncurses::KEY_LEFT  => Direction::Left,
+                key => match self.keys.direction(key) {
+                    Some(dir) => dir,
+                    None => return,
+                },
+            };
+            self.replay.record(self.board.tick, dir);
+            self.turn(dir);
         }
     }
 
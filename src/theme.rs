@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::fs;
+
+use ncurses::attr_t;
+
+use crate::colour::Colour;
+
+pub const DEFAULT_THEME: &str = "classic";
+/// The theme used on terminals without colour.
+pub const MONOCHROME_THEME: &str = "monochrome";
+const THEMES_DIR: &str = "themes";
+
+// A theme file styles every brick type and UI element, one per line:
+//
+//   description Bold glyphs on black
+//   wall    space  white  white
+//   head    ^>v<   black  yellow  bold         glyphs for up, right, down, left
+//   portal  @      -      black   bold,reverse  - takes the brick's colour
+//
+// Glyphs are one character, `space`, or four characters for the elements
+// that face a direction. Colours are basic colour names, numbers of the 256
+// colour palette or `#rrggbb`, and get replaced by the closest colour the
+// terminal has. Elements a theme leaves out keep the classic style.
+
+const BUILT_IN: [(&str, &str); 4] = [
+    ("classic", include_str!("../themes/classic.theme")),
+    ("high_contrast", include_str!("../themes/high_contrast.theme")),
+    ("retro", include_str!("../themes/retro.theme")),
+    ("monochrome", include_str!("../themes/monochrome.theme")),
+];
+
+pub const ELEMENTS: [&str; 16] = [
+    "none", "wall", "food", "ice", "open_wall", "switch", "dynamic_wall",
+    "head", "body", "gate", "conveyor", "portal", "key", "door",
+    "points", "ending",
+];
+
+#[derive(Clone)]
+pub struct ElementStyle {
+    pub glyphs: Vec<char>,
+    /// None for elements coloured by the brick they draw.
+    pub fg: Option<Colour>,
+    pub bg: Colour,
+    pub attr: attr_t,
+}
+
+impl ElementStyle {
+    pub fn glyph(&self, i: usize) -> char {
+        self.glyphs[i % self.glyphs.len()]
+    }
+}
+
+#[derive(Clone)]
+pub struct Theme {
+    pub description: String,
+    styles: HashMap<String, ElementStyle>,
+}
+
+fn parse_attributes(list: &str) -> Option<attr_t> {
+    list.split(',').try_fold(0, |attr, name| {
+        Some(attr | match name {
+            "bold" => ncurses::A_BOLD(),
+            "dim" => ncurses::A_DIM(),
+            "reverse" => ncurses::A_REVERSE(),
+            "underline" => ncurses::A_UNDERLINE(),
+            "blink" => ncurses::A_BLINK(),
+            "standout" => ncurses::A_STANDOUT(),
+            _ => return None
+        })
+    })
+}
+
+fn parse_style(line: &str) -> Result<(String, ElementStyle), String> {
+    let mut iter = line.split_whitespace();
+    let element = iter.next().unwrap_or_default();
+    if !ELEMENTS.contains(&element) {
+        return Err(format!("unknown element '{}'", element));
+    }
+    let glyphs: Vec<char> = match iter.next() {
+        Some("space") => vec![' '],
+        Some(glyphs) if glyphs.chars().count() == 1 || glyphs.chars().count() == 4 => glyphs.chars().collect(),
+        _ => return Err(format!("{} needs one or four glyphs", element)),
+    };
+    let fg = match iter.next() {
+        Some("-") => None,
+        Some(fg) => Some(Colour::parse(fg).ok_or_else(|| format!("unknown colour '{}'", fg))?),
+        None => return Err(format!("{} needs a foreground colour", element)),
+    };
+    let bg = iter.next().ok_or_else(|| format!("{} needs a background colour", element))?;
+    let bg = Colour::parse(bg).ok_or_else(|| format!("unknown colour '{}'", bg))?;
+    let attr = match iter.next() {
+        Some(list) => parse_attributes(list).ok_or_else(|| format!("unknown attributes '{}'", list))?,
+        None => 0,
+    };
+    Ok((element.to_owned(), ElementStyle { glyphs, fg, bg, attr }))
+}
+
+impl Theme {
+    /// Reads a theme on top of `base`, or a complete one without a base.
+    fn parse(text: &str, base: Option<&Theme>) -> Result<Theme, String> {
+        let mut theme = Theme {
+            description: String::new(),
+            styles: base.map(|base| base.styles.clone()).unwrap_or_default(),
+        };
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(description) = line.strip_prefix("description ") {
+                theme.description = description.trim().to_owned();
+                continue;
+            }
+            let (element, style) = parse_style(line).map_err(|err| format!("line {}: {}", i + 1, err))?;
+            theme.styles.insert(element, style);
+        }
+        match ELEMENTS.iter().find(|element| !theme.styles.contains_key(**element)) {
+            Some(element) => Err(format!("missing element '{}'", element)),
+            None => Ok(theme),
+        }
+    }
+
+    pub fn classic() -> Theme {
+        Theme::parse(BUILT_IN[0].1, None).expect("the classic theme is valid")
+    }
+
+    /// Loads a built-in theme, or `themes/<name>.theme`.
+    pub fn load(name: &str) -> Result<Theme, String> {
+        let text = match BUILT_IN.iter().find(|(built_in, _)| *built_in == name) {
+            Some((_, text)) => text.to_string(),
+            None => fs::read_to_string(format!("{}/{}.theme", THEMES_DIR, name)).map_err(|err| err.to_string())?,
+        };
+        Theme::parse(&text, Some(&Theme::classic()))
+    }
+
+    /// The built-in themes followed by the other files in the themes directory.
+    pub fn list() -> Vec<String> {
+        let mut names: Vec<String> = BUILT_IN.iter().map(|(name, _)| name.to_string()).collect();
+        let mut files: Vec<String> = match fs::read_dir(THEMES_DIR) {
+            Ok(dir) => dir.filter_map(|entry| {
+                let path = entry.ok()?.path();
+                (path.extension()? == "theme").then_some(path.file_stem()?.to_str()?.to_owned())
+            }).collect(),
+            Err(_) => Vec::new(),
+        };
+        files.sort();
+        files.retain(|name| !names.contains(name));
+        names.extend(files);
+        names
+    }
+
+    pub fn get(&self, element: &str) -> &ElementStyle {
+        &self.styles[element]
+    }
+
+    /// The colour an element is reduced to when drawn as a block.
+    pub fn block_colour(&self, element: &str) -> Option<Colour> {
+        let style = self.get(element);
+        match style.fg {
+            _ if style.bg != Colour::Index(ncurses::COLOR_BLACK) => Some(style.bg),
+            fg => fg,
+        }
+    }
+}
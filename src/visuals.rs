@@ -3,6 +3,15 @@ use std::rc::Rc;
 
 use crate::board::*;
 use crate::basic_window::*;
+use crate::colour::{Colour, ColourManager};
+use crate::theme::{self, ElementStyle, Theme};
+
+/// Box drawing characters by the sides they join: up, right, down and
+/// left are bits 0 to 3.
+const BOX_LINES: [char; 16] = [
+    '\u{b7}', '\u{2575}', '\u{2576}', '\u{2514}', '\u{2577}', '\u{2502}', '\u{250c}', '\u{251c}',
+    '\u{2574}', '\u{2518}', '\u{2500}', '\u{2534}', '\u{2510}', '\u{2524}', '\u{252c}', '\u{253c}',
+];
 
 pub struct VisualsRegistry {
     pub common_visuals: Rc<CommonVisuals>,
@@ -11,78 +20,275 @@ pub struct VisualsRegistry {
 
 impl VisualsRegistry {
 
-    pub fn build() -> Option<VisualsRegistry> {
-        let common_visuals = Rc::new(CommonVisuals::build()?.into());
-        Some(VisualsRegistry{
-            snake_visuals: SnakeVisuals::build(Rc::clone(&common_visuals))?.into(),
-            common_visuals
-        })
+    /// Uses the classic theme where the terminal has enough colour pairs
+    /// and the monochrome one everywhere else.
+    pub fn build(colours: Rc<ColourManager>) -> VisualsRegistry {
+        let colour = colours.has_colour() && ncurses::COLOR_PAIRS() > MIN_COLOR_PAIRS;
+        let coloured = colour.then(|| CommonVisuals::build(Rc::clone(&colours))).flatten().and_then(|common| {
+            let common = Rc::new(common);
+            Some((SnakeVisuals::build(Rc::clone(&common), &Theme::classic())?, common))
+        });
+        let (snake_visuals, common_visuals) = coloured.unwrap_or_else(|| {
+            let common = Rc::new(CommonVisuals::monochrome(colours));
+            let theme = Theme::load(theme::MONOCHROME_THEME).expect("the monochrome theme is valid");
+            (SnakeVisuals::build(Rc::clone(&common), &theme).expect("monochrome visuals need no colour pairs"), common)
+        });
+        VisualsRegistry {
+            snake_visuals: snake_visuals.into(),
+            common_visuals,
+        }
+    }
+
+    /// Rebuilds the snake visuals from `theme`. Returns false, keeping the
+    /// current ones, when the terminal runs out of colour pairs or has no
+    /// colour to show the theme in.
+    pub fn apply_theme(&self, theme: &Theme) -> bool {
+        if !self.common_visuals.has_colour() {
+            return false;
+        }
+        match SnakeVisuals::build(Rc::clone(&self.common_visuals), theme) {
+            Some(visuals) => {
+                *self.snake_visuals.borrow_mut() = visuals;
+                true
+            },
+            None => false,
+        }
     }
 }
 
+/// A pair for every basic colour and one for every themed element.
+const MIN_COLOR_PAIRS: i32 = 8 + theme::ELEMENTS.len() as i32;
+
 pub struct CommonVisuals {
-    pub basic_colors: Vec<ColorPair>
+    pub colours: Rc<ColourManager>,
+    /// Empty on terminals without colour.
+    pub basic_colors: Vec<ColorPair>,
 }
 
 impl CommonVisuals {
 
-    fn make_basic_colors() -> Option<Vec<ColorPair>> {
-        (ncurses::COLOR_BLACK..ncurses::COLOR_WHITE + 1).map(|c| ColorPair::new(c, ncurses::COLOR_BLACK)).collect()
+    fn make_basic_colors(colours: &ColourManager) -> Option<Vec<ColorPair>> {
+        (ncurses::COLOR_BLACK..ncurses::COLOR_WHITE + 1)
+            .map(|c| colours.pair(Colour::Index(c), Colour::Index(ncurses::COLOR_BLACK)))
+            .collect()
+    }
+
+    /// The colour `c` on black, or no attributes without colour.
+    pub fn get_colour(&self, c: i16) -> Attributes {
+        self.basic_colors.get(c as usize).map_or(Attributes::none(), |pair| (*pair).into())
+    }
+
+    pub fn has_colour(&self) -> bool {
+        !self.basic_colors.is_empty()
     }
 
-    pub fn get_colour(&self, c: i16) -> ColorPair {
-        self.basic_colors[c as usize]
+    /// A pair for `fg` on `bg`, or None without colour or free pairs.
+    pub fn pair(&self, fg: Colour, bg: Colour) -> Option<ColorPair> {
+        if !self.has_colour() {
+            return None;
+        }
+        self.colours.pair(fg, bg)
     }
 
-    pub fn build() -> Option<CommonVisuals> {
+    pub fn build(colours: Rc<ColourManager>) -> Option<CommonVisuals> {
         Some(CommonVisuals{
-            basic_colors: CommonVisuals::make_basic_colors()?,
+            basic_colors: CommonVisuals::make_basic_colors(&colours)?,
+            colours,
+        })
+    }
+
+    pub fn monochrome(colours: Rc<ColourManager>) -> CommonVisuals {
+        CommonVisuals {
+            colours,
+            basic_colors: Vec::new(),
+        }
+    }
+}
+
+/// A themed element: its glyph, or one glyph per direction for elements
+/// that face one, with the attributes it is drawn in.
+#[derive(Copy, Clone)]
+pub struct Glyphs {
+    chars: [char; 4],
+    attr: Attributes,
+}
+
+impl Glyphs {
+    /// Elements without a foreground colour get only the theme's
+    /// attributes and are coloured when drawn, as does everything on
+    /// terminals without colour.
+    fn build(style: &ElementStyle, common: &CommonVisuals) -> Option<Glyphs> {
+        let colour: Attributes = match style.fg {
+            Some(fg) if common.has_colour() => common.pair(fg, style.bg)?.into(),
+            _ => Attributes::none(),
+        };
+        Some(Glyphs {
+            chars: [0, 1, 2, 3].map(|i| style.glyph(i)),
+            attr: colour | Attributes::new(style.attr),
         })
     }
+
+    pub fn get(&self) -> PrintableCharacter {
+        PrintableCharacter::new(self.chars[0], self.attr)
+    }
+
+    pub fn facing(&self, dir: Direction) -> PrintableCharacter {
+        PrintableCharacter::new(self.chars[dir as usize], self.attr)
+    }
+
+    pub fn coloured(&self, colour: Attributes) -> PrintableCharacter {
+        PrintableCharacter::new(self.chars[0], self.attr | colour)
+    }
+
+    pub fn attr(&self) -> Attributes {
+        self.attr
+    }
 }
 
 pub struct SnakeVisuals {
-    pub none: PrintableCharacter,
-    pub wall: PrintableCharacter,
-    pub food: PrintableCharacter,
-    pub colors_head: ColorPair,
-    pub colors_body: ColorPair,
-    pub colors_points: ColorPair,
-    pub colors_ending: ColorPair,
+    pub none: Glyphs,
+    pub wall: Glyphs,
+    pub food: Glyphs,
+    pub ice: Glyphs,
+    pub open_wall: Glyphs,
+    pub switch: Glyphs,
+    pub dynamic_wall: Glyphs,
+    pub head: Glyphs,
+    pub body: Glyphs,
+    pub gate: Glyphs,
+    pub conveyor: Glyphs,
+    pub portal: Glyphs,
+    pub key: Glyphs,
+    pub door: Glyphs,
+    pub colors_points: Attributes,
+    pub colors_ending: Attributes,
+    pub theme: Theme,
     pub common_visuals: Rc<CommonVisuals>,
 }
 
 impl SnakeVisuals {
-    fn build(common: Rc<CommonVisuals>) -> Option<SnakeVisuals> {
+    fn build(common: Rc<CommonVisuals>, theme: &Theme) -> Option<SnakeVisuals> {
+        let glyphs = |element| Glyphs::build(theme.get(element), &common);
         Some(SnakeVisuals {
-            none:       PrintableCharacter::new(' ', ColorPair::new(ncurses::COLOR_BLACK, ncurses::COLOR_BLACK)?.into()),
-            wall:       PrintableCharacter::new(' ', ColorPair::new(ncurses::COLOR_BLACK, ncurses::COLOR_BLUE )?.into()),
-            food:       PrintableCharacter::new('*', ColorPair::new(ncurses::COLOR_GREEN, ncurses::COLOR_BLACK)?.into()),
-            colors_head: ColorPair::new(ncurses::COLOR_RED,    ncurses::COLOR_BLACK)?,
-            colors_body: ColorPair::new(ncurses::COLOR_YELLOW, ncurses::COLOR_BLACK)?,
-            colors_points: ColorPair::new(ncurses::COLOR_MAGENTA, ncurses::COLOR_WHITE)?,
-            colors_ending: ColorPair::new(ncurses::COLOR_CYAN, ncurses::COLOR_WHITE)?,
+            none: glyphs("none")?,
+            wall: glyphs("wall")?,
+            food: glyphs("food")?,
+            ice: glyphs("ice")?,
+            open_wall: glyphs("open_wall")?,
+            switch: glyphs("switch")?,
+            dynamic_wall: glyphs("dynamic_wall")?,
+            head: glyphs("head")?,
+            body: glyphs("body")?,
+            gate: glyphs("gate")?,
+            conveyor: glyphs("conveyor")?,
+            portal: glyphs("portal")?,
+            key: glyphs("key")?,
+            door: glyphs("door")?,
+            colors_points: glyphs("points")?.attr(),
+            colors_ending: glyphs("ending")?.attr(),
+            theme: theme.clone(),
             common_visuals: common
         })
     }
 
-    fn get_char_from_direction(dir: Direction) -> char {
-        match dir {
-            Direction::Up => '^',
-            Direction::Right => '>',
-            Direction::Down => 'v',
-            Direction::Left => '<',
+    /// The colour a brick is reduced to when it shares a character.
+    fn block_colour(&self, category: &BrickType) -> Colour {
+        let element = match category {
+            BrickType::None | BrickType::GroupWall(_, false) => "none",
+            BrickType::Wall | BrickType::GroupWall(_, true) => "wall",
+            BrickType::Snake(..) => "body",
+            BrickType::SnakeHead(_) => "head",
+            BrickType::Food => "food",
+            BrickType::Portal(data) => return Colour::Index(data.colour),
+            BrickType::Gate(_) => "gate",
+            BrickType::Key(colour) | BrickType::Door(colour) => return Colour::Index(*colour),
+            BrickType::Ice => "ice",
+            BrickType::Conveyor(_) => "conveyor",
+            BrickType::Switch(_) => "switch",
+            BrickType::DynamicWall => "dynamic_wall",
+        };
+        self.theme.block_colour(element).unwrap_or(Colour::Index(ncurses::COLOR_BLACK))
+    }
+
+    /// Draws two vertically adjacent cells as one half block character.
+    pub fn get_pair(&self, top: &BrickType, bottom: Option<&BrickType>) -> Option<WideCharacter> {
+        let top = self.block_colour(top);
+        let bottom = bottom.map_or(Colour::Index(ncurses::COLOR_BLACK), |bottom| self.block_colour(bottom));
+        let c = if top == bottom { '\u{2588}' } else { '\u{2580}' };
+        Some(WideCharacter::new(c, self.common_visuals.pair(top, bottom)?.into()))
+    }
+
+    /// Two character glyphs for bricks drawn two columns wide. Bricks
+    /// without one repeat their single character.
+    fn get_double_glyph(category: &BrickType) -> Option<[char; 2]> {
+        match category {
+            BrickType::Snake(_, dir) | BrickType::SnakeHead(dir) | BrickType::Gate(dir) | BrickType::Conveyor(dir) => {
+                Some(match dir {
+                    Direction::Up => ['/', '\\'],
+                    Direction::Right => ['-', '>'],
+                    Direction::Down => ['\\', '/'],
+                    Direction::Left => ['<', '-'],
+                })
+            },
+            BrickType::Food => Some(['(', ')']),
+            BrickType::Key(_) => Some(['o', '-']),
+            BrickType::Switch(_) => Some(['[', ']']),
+            _ => None,
+        }
+    }
+
+    pub fn get_double(&self, category: &BrickType) -> [PrintableCharacter; 2] {
+        let single = self.get(category);
+        let numbered = matches!(category, BrickType::Key(_) | BrickType::Door(_)) && !self.common_visuals.has_colour();
+        match Self::get_double_glyph(category).filter(|_| !numbered) {
+            Some([left, right]) => [single.with_char(left), single.with_char(right)],
+            None => [single, single],
+        }
+    }
+
+    /// A body segment drawn as a line joining the segments next to it, or
+    /// as a stub pointing at the one ahead for the tail. The second
+    /// character carries horizontal lines on to the next cell when cells
+    /// are two columns wide.
+    pub fn get_smooth(&self, category: &BrickType, tail: bool) -> Option<[WideCharacter; 2]> {
+        let (entered, left) = match category {
+            BrickType::Snake(entered, left) => (*entered, *left),
+            _ => return None,
+        };
+        let side = |dir: Direction| 1 << dir as usize;
+        let sides = side(left) | if tail { 0 } else { side(entered.mirror()) };
+        let right = if sides & side(Direction::Right) != 0 { BOX_LINES[10] } else { ' ' };
+        let attr = self.body.attr();
+        Some([WideCharacter::new(BOX_LINES[sides], attr), WideCharacter::new(right, attr)])
+    }
+
+    /// A key or door in `colour`. Without colour to tell them apart the
+    /// number of the colour is drawn in place of the glyph.
+    fn colour_coded(&self, glyphs: &Glyphs, colour: i16) -> PrintableCharacter {
+        let character = glyphs.coloured(self.common_visuals.get_colour(colour));
+        match char::from_digit(colour as u32, 10) {
+            Some(digit) if !self.common_visuals.has_colour() => character.with_char(digit),
+            _ => character,
         }
     }
 
     pub fn get(&self, category: &BrickType) -> PrintableCharacter {
         match category {
-            BrickType::None => self.none,
-            BrickType::Wall => self.wall,
-            BrickType::Snake(dir) => PrintableCharacter::new(Self::get_char_from_direction(*dir), self.colors_body.into()),
-            BrickType::SnakeHead(dir) => PrintableCharacter::new(Self::get_char_from_direction(*dir), self.colors_head.into()),
-            BrickType::Food => self.food,
-            BrickType::Portal(data) => PrintableCharacter::new('@', self.common_visuals.get_colour(data.colour).into())
+            BrickType::None => self.none.get(),
+            BrickType::Wall => self.wall.get(),
+            BrickType::Snake(_, dir) => self.body.facing(*dir),
+            BrickType::SnakeHead(dir) => self.head.facing(*dir),
+            BrickType::Food => self.food.get(),
+            BrickType::Portal(data) => self.portal.coloured(self.common_visuals.get_colour(data.colour)),
+            BrickType::Gate(dir) => self.gate.facing(*dir),
+            BrickType::Key(colour) => self.colour_coded(&self.key, *colour),
+            BrickType::Door(colour) => self.colour_coded(&self.door, *colour),
+            BrickType::Ice => self.ice.get(),
+            BrickType::Conveyor(dir) => self.conveyor.facing(*dir),
+            BrickType::GroupWall(_, true) => self.wall.get(),
+            BrickType::GroupWall(_, false) => self.open_wall.get(),
+            BrickType::Switch(_) => self.switch.get(),
+            BrickType::DynamicWall => self.dynamic_wall.get(),
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,411 @@
+use crate::basic_window::*;
+use crate::layout::{self, Size, Split};
+
+/// A border around an area with a title on its top edge. The widgets
+/// inside it are windows of their own, placed in `inner`.
+pub struct Frame {
+    win: BasicWindow,
+    dim: Dimensions,
+    title: String,
+}
+
+impl Frame {
+    pub fn new(dim: Dimensions, title: &str) -> Frame {
+        Frame {
+            win: BasicWindow::new(dim),
+            dim,
+            title: title.to_owned(),
+        }
+    }
+
+    /// The area inside the border.
+    pub fn inner(&self) -> Dimensions {
+        self.dim.inset(1)
+    }
+}
+
+impl Window for Frame {
+    fn refresh(&self) {
+        self.win.refresh();
+    }
+
+    fn draw(&self) {
+        self.win.erase();
+        self.win.draw_border();
+        if !self.title.is_empty() {
+            self.win.apply_attr(Attributes::new(ncurses::A_BOLD()));
+            self.win.move_print(0, 2, &format!(" {} ", self.title));
+            self.win.clear_attr();
+        }
+        self.refresh();
+    }
+
+    fn handle_keypress(&mut self, _key: i32) {}
+}
+
+/// Lines of text, cut off at the edges of the window.
+pub struct Label {
+    win: BasicWindow,
+    lines: Vec<String>,
+    centred: bool,
+}
+
+impl Label {
+    pub fn new(dim: Dimensions, lines: Vec<String>) -> Label {
+        Label {
+            win: BasicWindow::new(dim),
+            lines,
+            centred: false,
+        }
+    }
+
+    /// Centres every line in the window.
+    pub fn centred(mut self) -> Label {
+        self.centred = true;
+        self
+    }
+}
+
+impl Window for Label {
+    fn refresh(&self) {
+        self.win.refresh();
+    }
+
+    fn draw(&self) {
+        self.win.erase();
+        let width = self.win.width() as usize;
+        for (i, line) in self.lines.iter().enumerate().take(self.win.height() as usize) {
+            let line: String = line.chars().take(width).collect();
+            let column = if self.centred { (width - line.chars().count()) / 2 } else { 0 };
+            self.win.move_print(i as i32, column as i32, &line);
+        }
+        self.refresh();
+    }
+
+    fn handle_keypress(&mut self, _key: i32) {}
+}
+
+/// A line of text typed in by the player. Only the characters `allowed`
+/// lets through are taken, up to `max_len` of them.
+pub struct TextInput {
+    win: BasicWindow,
+    value: String,
+    max_len: usize,
+    allowed: fn(char) -> bool,
+    focused: bool,
+}
+
+impl TextInput {
+    pub fn new(dim: Dimensions, max_len: usize, allowed: fn(char) -> bool) -> TextInput {
+        TextInput {
+            win: BasicWindow::new(dim),
+            value: String::new(),
+            max_len,
+            allowed,
+            focused: false,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl Window for TextInput {
+    fn refresh(&self) {
+        self.win.refresh();
+    }
+
+    /// Draws the field underlined, showing the end of the text when it
+    /// is longer than the window, with a cursor after it while focused.
+    fn draw(&self) {
+        self.win.erase();
+        let width = (self.win.width() - 1).max(0) as usize;
+        let len = self.value.chars().count();
+        let shown: String = self.value.chars().skip(len.saturating_sub(width)).collect();
+        self.win.apply_attr(Attributes::new(ncurses::A_UNDERLINE()));
+        self.win.move_print(0, 0, &format!("{:width$}", shown, width = width + 1));
+        self.win.clear_attr();
+        if self.focused {
+            self.win.apply_attr(Attributes::new(ncurses::A_REVERSE()));
+            self.win.move_print(0, shown.chars().count() as i32, " ");
+            self.win.clear_attr();
+        }
+        self.refresh();
+    }
+
+    fn handle_keypress(&mut self, key: i32) {
+        if key == KEY_DELETE || key == ncurses::KEY_BACKSPACE {
+            self.value.pop();
+        } else if let Some(c) = char::from_u32(key as u32).filter(|c| (self.allowed)(*c)) {
+            if self.value.chars().count() < self.max_len {
+                self.value.push(c);
+            }
+        }
+        self.draw();
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn set_focus(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+}
+
+/// A bar filled in proportion to `value` out of `max`, followed by both.
+pub struct ProgressBar {
+    win: BasicWindow,
+    value: usize,
+    max: usize,
+}
+
+impl ProgressBar {
+    pub fn new(dim: Dimensions, value: usize, max: usize) -> ProgressBar {
+        ProgressBar {
+            win: BasicWindow::new(dim),
+            value: value.min(max),
+            max,
+        }
+    }
+}
+
+impl Window for ProgressBar {
+    fn refresh(&self) {
+        self.win.refresh();
+    }
+
+    fn draw(&self) {
+        self.win.erase();
+        let count = format!(" {}/{}", self.value, self.max);
+        let width = (self.win.width() as usize).saturating_sub(count.len() + 2);
+        let filled = (width * self.value).checked_div(self.max).unwrap_or(width);
+        self.win.move_print(0, 0, &format!("[{}{}]{}", "#".repeat(filled), "-".repeat(width - filled), count));
+        self.refresh();
+    }
+
+    fn handle_keypress(&mut self, _key: i32) {}
+}
+
+/// A row of buttons, the highlighted one moved with the left and right
+/// arrows.
+pub struct Buttons {
+    win: BasicWindow,
+    labels: Vec<String>,
+    selected: usize,
+    focused: bool,
+}
+
+impl Buttons {
+    pub fn new(dim: Dimensions, labels: Vec<String>) -> Buttons {
+        Buttons {
+            win: BasicWindow::new(dim),
+            labels,
+            selected: 0,
+            focused: false,
+        }
+    }
+
+    /// The width of the buttons with the gaps between them.
+    fn width(labels: &[String]) -> usize {
+        labels.iter().map(|label| label.chars().count() + 4).sum::<usize>() + labels.len().saturating_sub(1)
+    }
+}
+
+impl Window for Buttons {
+    fn refresh(&self) {
+        self.win.refresh();
+    }
+
+    /// Centres the buttons. The highlighted one is reversed while the row
+    /// has the focus and bold otherwise.
+    fn draw(&self) {
+        self.win.erase();
+        let mut column = (self.win.width() - Buttons::width(&self.labels) as i32).max(0) / 2;
+        for (i, label) in self.labels.iter().enumerate() {
+            if i == self.selected {
+                let attr = if self.focused { ncurses::A_REVERSE() } else { ncurses::A_BOLD() };
+                self.win.apply_attr(Attributes::new(attr));
+            }
+            let button = format!("[ {} ]", label);
+            self.win.move_print(0, column, &button);
+            self.win.clear_attr();
+            column += button.chars().count() as i32 + 1;
+        }
+        self.refresh();
+    }
+
+    fn handle_keypress(&mut self, key: i32) {
+        let len = self.labels.len().max(1);
+        match key {
+            ncurses::KEY_LEFT => self.selected = (self.selected + len - 1) % len,
+            ncurses::KEY_RIGHT => self.selected = (self.selected + 1) % len,
+            _ => return,
+        }
+        self.draw();
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn set_focus(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+}
+
+/// Which of a group of windows the keypresses go to. Tab and shift tab
+/// move the focus between the ones that take it.
+pub struct Focus {
+    current: usize,
+}
+
+impl Focus {
+    /// Focuses the first window that takes the focus.
+    pub fn new(windows: &mut [&mut dyn Window]) -> Focus {
+        let focus = Focus { current: windows.iter().position(|win| win.focusable()).unwrap_or(0) };
+        for (i, win) in windows.iter_mut().enumerate() {
+            win.set_focus(i == focus.current);
+        }
+        focus
+    }
+
+    /// The next window after the current one that takes the focus, or
+    /// the one before it when going backwards. Wraps around.
+    fn next(&self, windows: &[&mut dyn Window], forward: bool) -> Option<usize> {
+        let len = windows.len();
+        (1..=len)
+            .map(|step| if forward { (self.current + step) % len } else { (self.current + len - step) % len })
+            .find(|i| windows[*i].focusable())
+    }
+
+    fn move_focus(&mut self, windows: &mut [&mut dyn Window], forward: bool) {
+        let next = match self.next(windows, forward) {
+            Some(next) => next,
+            None => return,
+        };
+        windows[self.current].set_focus(false);
+        windows[self.current].draw();
+        self.current = next;
+        windows[next].set_focus(true);
+        windows[next].draw();
+    }
+
+    pub fn handle_keypress(&mut self, windows: &mut [&mut dyn Window], key: i32) {
+        match key {
+            KEY_TAB => self.move_focus(windows, true),
+            ncurses::KEY_BTAB => self.move_focus(windows, false),
+            key => {
+                if let Some(win) = windows.get_mut(self.current).filter(|win| win.focusable()) {
+                    win.handle_keypress(key);
+                }
+            },
+        }
+    }
+}
+
+/// The length of a dialog's input and the characters it takes.
+type InputRules = (usize, fn(char) -> bool);
+
+/// A framed window in the middle of the screen that takes every key
+/// until one of its buttons is pressed.
+pub struct Dialog {
+    frame: Frame,
+    message: Label,
+    input: Option<TextInput>,
+    buttons: Buttons,
+}
+
+impl Dialog {
+    pub fn new(title: &str, message: Vec<String>, buttons: Vec<String>) -> Dialog {
+        Dialog::build(title, message, buttons, None)
+    }
+
+    /// A dialog with a text input under the message.
+    pub fn prompt(title: &str, message: Vec<String>, buttons: Vec<String>, max_len: usize, allowed: fn(char) -> bool) -> Dialog {
+        Dialog::build(title, message, buttons, Some((max_len, allowed)))
+    }
+
+    fn build(title: &str, message: Vec<String>, buttons: Vec<String>, input: Option<InputRules>) -> Dialog {
+        let content_width = message.iter().map(|line| line.chars().count())
+            .chain([title.chars().count() + 2, Buttons::width(&buttons)])
+            .chain(input.map(|(max_len, _)| max_len + 1))
+            .max()
+            .unwrap_or(0) as i32;
+        let input_lines = if input.is_some() { 2 } else { 0 };
+        let height = message.len() as i32 + input_lines + 4;
+        let frame = Frame::new(Dimensions::centred(height, content_width + 4), title);
+
+        let content = layout::split(&frame.inner(), Split::Horizontal, &[Size::Fixed(1), Size::Flex(1), Size::Fixed(1)])[1];
+        let areas = layout::split(&content, Split::Vertical, &[
+            Size::Fixed(message.len() as i32),
+            Size::Fixed(input_lines),
+            Size::Flex(1),
+            Size::Fixed(1),
+        ]);
+        let input_area = layout::split(&areas[1], Split::Vertical, &[Size::Fixed(1), Size::Fixed(1)])[1];
+        Dialog {
+            message: Label::new(areas[0], message).centred(),
+            input: input.map(|(max_len, allowed)| TextInput::new(input_area, max_len, allowed)),
+            buttons: Buttons::new(areas[3], buttons),
+            frame,
+        }
+    }
+
+    /// The text typed in, empty for dialogs without an input.
+    pub fn input(&self) -> &str {
+        self.input.as_ref().map_or("", TextInput::value)
+    }
+
+    fn windows(&mut self) -> Vec<&mut dyn Window> {
+        let mut windows: Vec<&mut dyn Window> = Vec::new();
+        if let Some(input) = &mut self.input {
+            windows.push(input);
+        }
+        windows.push(&mut self.buttons);
+        windows
+    }
+
+    /// Lays the dialog out again in the middle of the screen, keeping what
+    /// was typed in, the highlighted button and which of them has focus.
+    fn place(&mut self) {
+        let input = self.input.as_ref().map(|input| (input.max_len, input.allowed));
+        let mut placed = Dialog::build(&self.frame.title, self.message.lines.clone(), self.buttons.labels.clone(), input);
+        if let (Some(placed), Some(input)) = (&mut placed.input, &self.input) {
+            placed.value = input.value.clone();
+            placed.focused = input.focused;
+        }
+        placed.buttons.selected = self.buttons.selected;
+        placed.buttons.focused = self.buttons.focused;
+        *self = placed;
+    }
+
+    fn draw(&mut self) {
+        self.frame.draw();
+        self.message.draw();
+        for win in self.windows() {
+            win.draw();
+        }
+    }
+
+    /// Runs the dialog until a button is pressed and gives its index, or
+    /// None when escape closes it. Enter presses the highlighted button,
+    /// from the input as well.
+    pub fn run(&mut self, context: &NcursesContext) -> Option<usize> {
+        let mut focus = Focus::new(&mut self.windows());
+        self.draw();
+        loop {
+            match context.get_key() {
+                KEY_ESCAPE => return None,
+                KEY_NEWLINE => return Some(self.buttons.selected),
+                ncurses::KEY_RESIZE => {
+                    clear_screen();
+                    self.place();
+                    self.draw();
+                },
+                key => focus.handle_keypress(&mut self.windows(), key),
+            }
+        }
+    }
+}